@@ -0,0 +1,89 @@
+use crate::response::VadOutput;
+use anyhow::Result;
+use std::fmt;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// Live load of one backend endpoint, for progress displays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointStats {
+    /// Endpoint URL, or another name for non-HTTP backends.
+    pub name: String,
+    /// Requests currently in flight.
+    pub in_flight: usize,
+    /// Smoothed request latency, once a request has completed.
+    pub latency: Option<Duration>,
+    /// Whether the endpoint currently takes requests (healthy and not ejected).
+    pub available: bool,
+}
+
+/// A failed backend request, attached to errors so callers can tell which
+/// endpoint failed and with what HTTP status.
+///
+/// Retrieve it with `error.downcast_ref::<RequestError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestError {
+    /// Endpoint of the last attempt.
+    pub endpoint: String,
+    /// HTTP status of the last response, or `None` if none was received.
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// Something that runs VAD on one file at a time.
+///
+/// [`BatchJob`](crate::BatchJob) handles discovery, validation, skipping and
+/// reporting, and hands every remaining file to a backend. Implemented by the
+/// HTTP [`VadClient`](crate::VadClient) and the built-in [`LocalVad`](crate::LocalVad).
+pub trait Backend: Send + Sync + fmt::Debug {
+    /// Runs VAD on `input_file`, holding `audio` of audio, and writes its
+    /// results into `output_dir`.
+    fn process(&self, input_file: &Path, audio: Duration, output_dir: &Path) -> Result<VadOutput>;
+
+    /// Maximum number of files the backend can usefully process at once.
+    fn capacity(&self) -> usize;
+
+    /// Blocks until the backend is ready to accept work.
+    fn wait_until_ready(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs background maintenance until `stop` is signalled or its sender is dropped.
+    fn monitor(&self, _stop: &Receiver<()>) {}
+
+    /// Current per-endpoint load; empty for backends without endpoints.
+    fn endpoint_stats(&self) -> Vec<EndpointStats> {
+        Vec::new()
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+    fn process(&self, input_file: &Path, audio: Duration, output_dir: &Path) -> Result<VadOutput> {
+        (**self).process(input_file, audio, output_dir)
+    }
+
+    fn capacity(&self) -> usize {
+        (**self).capacity()
+    }
+
+    fn wait_until_ready(&self) -> Result<()> {
+        (**self).wait_until_ready()
+    }
+
+    fn monitor(&self, stop: &Receiver<()>) {
+        (**self).monitor(stop)
+    }
+
+    fn endpoint_stats(&self) -> Vec<EndpointStats> {
+        (**self).endpoint_stats()
+    }
+}
@@ -0,0 +1,1180 @@
+use crate::backend::{Backend, RequestError};
+use crate::convert::{
+    CONVERT_DIR, ConvertOptions, ConvertedFile, is_convertible, needs_requantize_only,
+};
+use crate::decode::{DecodedAudio, Decoder, DecoderRegistry};
+use crate::filter::{PathFilter, PathMatcher, WalkError, WalkOptions, is_hidden};
+use crate::journal::{
+    Fingerprint, JOURNAL_FILE, Journal, JournalEntry, JournalStatus, SkipPolicy, unix_now,
+};
+use crate::metrics::Metrics;
+use crate::output::OutputFormat;
+use crate::plan::{Plan, PlanAction, PlannedFile};
+use crate::progress::{Progress, ProgressMode};
+use crate::report::{FileStatus, ReportRecord, RunReport};
+use crate::validate::{
+    Rejection, SampleStats, ValidationLimits, ValidationMode, deep_validate_wav,
+};
+use crate::wav::{is_target_format, wav_header};
+use anyhow::{Context, Result};
+use rayon::{ThreadPoolBuilder, prelude::*};
+use std::collections::HashSet;
+use std::fs::create_dir_all;
+use std::net::{SocketAddr, TcpListener};
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, error_span, info, warn};
+use walkdir::WalkDir;
+
+/// Files found by the walk that may wait for a worker before the walk blocks.
+const DISCOVERY_QUEUE: usize = 1024;
+
+/// What the backend returned for one processed file.
+///
+/// Segments are written to `segments.json` by the backend; only their count
+/// and total length travel on into the report, journal and run totals.
+struct FileRecord {
+    /// Endpoint that processed the file.
+    endpoint: String,
+    /// HTTP status of the response, if the file went through the API.
+    status: Option<u16>,
+    /// Duration of the audio sent, in seconds.
+    audio_secs: f64,
+    /// Number of speech segments and their total seconds, for JSON responses.
+    speech: Option<(usize, f64)>,
+}
+
+/// Summary of a finished batch run.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BatchReport {
+    /// Files the VAD API processed successfully.
+    pub processed: usize,
+    /// Files skipped as already processed.
+    pub already_done: usize,
+    /// Files rejected by validation.
+    pub invalid: usize,
+    /// Files the backend answered with an error status.
+    pub http_errors: usize,
+    /// Files that failed to read, convert or write, or whose request got no response.
+    pub io_errors: usize,
+    /// Whether the run stopped dispatching files after reaching
+    /// [`BatchJob::max_failures`].
+    pub aborted: bool,
+    /// Speech segments the server reported across all processed files.
+    pub segment_count: usize,
+    /// Total seconds of detected speech across all processed files.
+    pub speech_seconds: f64,
+    /// Directories and entries that could not be read while walking the input.
+    pub walk_errors: Vec<WalkError>,
+}
+
+impl BatchReport {
+    /// Files that failed, whether with an HTTP or an I/O error.
+    pub fn failed(&self) -> usize {
+        self.http_errors + self.io_errors
+    }
+
+    /// Files that did not reach the backend or failed there.
+    pub fn skipped(&self) -> usize {
+        self.already_done + self.invalid + self.failed()
+    }
+}
+
+/// How a single file ended.
+enum Outcome {
+    Processed(FileRecord),
+    AlreadyDone,
+    Invalid(Vec<Rejection>),
+}
+
+/// What is known about a file's trip through the backend, whether or not it succeeded.
+#[derive(Default)]
+struct FileMetrics {
+    /// Duration of the audio, once it was determined.
+    audio: Option<Duration>,
+    /// Time spent in the backend, retries included.
+    latency: Option<Duration>,
+}
+
+/// Classifies a failed file: an HTTP error if the backend answered, an I/O error otherwise.
+fn failure_status(error: &anyhow::Error) -> FileStatus {
+    match error.downcast_ref::<RequestError>() {
+        Some(request) if request.status.is_some() => FileStatus::HttpError,
+        _ => FileStatus::IoError,
+    }
+}
+
+/// Builds the run report's record for a file, `relative` to the input directory.
+fn report_record(relative: &Path, result: &Result<Outcome>, metrics: &FileMetrics) -> ReportRecord {
+    let status = match result {
+        Ok(Outcome::Processed(_)) => FileStatus::Processed,
+        Ok(Outcome::AlreadyDone) => FileStatus::AlreadyDone,
+        Ok(Outcome::Invalid(_)) => FileStatus::InvalidFormat,
+        Err(e) => failure_status(e),
+    };
+    let mut record = ReportRecord::new(relative, status);
+    record.latency_ms = metrics.latency.map(|l| l.as_millis() as u64);
+    record.audio_secs = metrics.audio.map(|a| a.as_secs_f64());
+    match result {
+        Ok(Outcome::Processed(file)) => {
+            record.endpoint = Some(file.endpoint.clone());
+            record.http_status = file.status;
+            record.segments = file.speech.map(|(segments, _)| segments);
+            record.speech_secs = file.speech.map(|(_, seconds)| seconds);
+        }
+        Ok(Outcome::AlreadyDone) => {}
+        Ok(Outcome::Invalid(rejections)) => record.rejections = rejections.clone(),
+        Err(e) => {
+            if let Some(request) = e.downcast_ref::<RequestError>() {
+                record.endpoint = Some(request.endpoint.clone());
+                record.http_status = request.status;
+            }
+            record.error = Some(format!("{e:#}"));
+        }
+    }
+    record
+}
+
+/// Whether `path` has a `.wav` extension, in any case.
+fn is_wav(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+}
+
+/// Resolves listed files against `input_dir`, dropping duplicates and files outside it.
+///
+/// Files that do not exist are kept, so they are reported as errors.
+fn resolve_listed(input_dir: &Path, files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let mut resolved = Vec::new();
+    for file in files {
+        let joined = input_dir.join(file);
+        let path = joined.canonicalize().unwrap_or(joined);
+        // Missing files cannot be canonicalized, so `..` is not resolved for them.
+        let escapes = path.components().any(|c| c == Component::ParentDir);
+        if escapes || !path.starts_with(input_dir) {
+            warn!(
+                file = %file.display(),
+                "Skipping listed file outside the input directory"
+            );
+            continue;
+        }
+        if seen.insert(path.clone()) {
+            resolved.push(path);
+        }
+    }
+    resolved
+}
+
+/// What the skip policy and validation decided about a file.
+enum Inspection<'a> {
+    AlreadyDone,
+    Invalid(Vec<Rejection>),
+    Ready(Source<'a>),
+}
+
+/// How a file that passed validation reaches the backend.
+enum Source<'a> {
+    /// A WAV file already in the target format.
+    Target { duration: Duration },
+    /// A WAV file that needs conversion.
+    Convert { duration: Duration },
+    /// A compressed file decoded during deep validation.
+    Decoded(DecodedAudio),
+    /// A compressed file not decoded yet.
+    Compressed(&'a dyn Decoder),
+}
+
+/// Logs why a file is skipped and turns the reasons into an [`Outcome`].
+fn reject(rejections: Vec<Rejection>) -> Outcome {
+    let reasons: Vec<String> = rejections.iter().map(ToString::to_string).collect();
+    warn!(reasons = %reasons.join("; "), "Skipping invalid file");
+    Outcome::Invalid(rejections)
+}
+
+/// A batch VAD run over a directory tree of WAV (and optionally compressed) files.
+///
+/// Built with [`BatchJob::new`] and configured with the chained setters before
+/// calling [`BatchJob::run`].
+#[derive(Debug)]
+pub struct BatchJob {
+    input_dir: PathBuf,
+    output_dir: PathBuf,
+    backend: Box<dyn Backend>,
+    max_concurrency: Option<usize>,
+    skip_policy: SkipPolicy,
+    journal_path: Option<PathBuf>,
+    convert: bool,
+    convert_options: ConvertOptions,
+    decoders: DecoderRegistry,
+    validation: ValidationMode,
+    validation_limits: ValidationLimits,
+    files: Option<Vec<PathBuf>>,
+    filter: PathFilter,
+    walk: WalkOptions,
+    progress: ProgressMode,
+    progress_interval: Duration,
+    report: Option<(PathBuf, OutputFormat)>,
+    max_failures: Option<usize>,
+    metrics_addr: Option<SocketAddr>,
+    metrics_textfile: Option<PathBuf>,
+    metrics_interval: Duration,
+}
+
+impl BatchJob {
+    /// Creates a job that mirrors `input_dir` into `output_dir` using `backend`,
+    /// typically a [`VadClient`](crate::VadClient) or [`LocalVad`](crate::LocalVad).
+    pub fn new(
+        input_dir: impl Into<PathBuf>,
+        output_dir: impl Into<PathBuf>,
+        backend: impl Backend + 'static,
+    ) -> Self {
+        Self {
+            input_dir: input_dir.into(),
+            output_dir: output_dir.into(),
+            backend: Box::new(backend),
+            max_concurrency: None,
+            skip_policy: SkipPolicy::default(),
+            journal_path: None,
+            convert: false,
+            convert_options: ConvertOptions::default(),
+            decoders: DecoderRegistry::new(),
+            validation: ValidationMode::default(),
+            validation_limits: ValidationLimits::default(),
+            files: None,
+            filter: PathFilter::default(),
+            walk: WalkOptions::default(),
+            progress: ProgressMode::Off,
+            progress_interval: Duration::from_secs(10),
+            report: None,
+            max_failures: None,
+            metrics_addr: None,
+            metrics_textfile: None,
+            metrics_interval: Duration::from_secs(15),
+        }
+    }
+
+    /// Converts files that are not 16 kHz mono 16-bit PCM instead of skipping them.
+    ///
+    /// Sources may use any channel count and sample rate with 8/16/24/32-bit
+    /// integer or 32-bit float samples.
+    ///
+    /// Converted copies are written to a scratch directory inside the output
+    /// directory, so shared-path API servers can read them, and removed after use.
+    pub fn convert(mut self, convert: bool) -> Self {
+        self.convert = convert;
+        self
+    }
+
+    /// Sets how files are converted when [`BatchJob::convert`] is enabled.
+    pub fn convert_options(mut self, options: ConvertOptions) -> Self {
+        self.convert_options = options;
+        self
+    }
+
+    /// Sets the decoders for non-WAV inputs; by default only WAV files are processed.
+    ///
+    /// Files with an extension handled by `decoders` are decoded and converted
+    /// to 16 kHz mono 16-bit PCM before VAD, regardless of [`BatchJob::convert`].
+    /// Their outputs use the input's relative path, extension included
+    /// (e.g. `talk.flac` goes to `<output_dir>/talk.flac/`).
+    pub fn decoders(mut self, decoders: DecoderRegistry) -> Self {
+        self.decoders = decoders;
+        self
+    }
+
+    /// Processes only `files` instead of walking the input directory.
+    ///
+    /// Relative paths are resolved against the input directory, so outputs
+    /// mirror the same layout as a full walk; absolute paths must point inside
+    /// it. Listed files are processed whatever their extension.
+    pub fn files(mut self, files: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.files = Some(files.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Narrows the inputs down with gitignore-style include and exclude patterns.
+    pub fn filter(mut self, filter: PathFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets how the input directory is traversed: symlinks, depth and hidden entries.
+    pub fn walk_options(mut self, walk: WalkOptions) -> Self {
+        self.walk = walk;
+        self
+    }
+
+    /// Sets how progress is shown on stderr; off by default.
+    ///
+    /// Files are processed while the input is still being walked, so the
+    /// total is reported as a lower bound until the walk has finished.
+    pub fn progress(mut self, mode: ProgressMode) -> Self {
+        self.progress = mode;
+        self
+    }
+
+    /// Sets the time between status lines in [`ProgressMode::Log`]; defaults to 10 seconds.
+    pub fn progress_interval(mut self, interval: Duration) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Writes a report of every file's outcome to `path` while the run progresses.
+    ///
+    /// See [`RunReport`] for the layout; unreadable parts of the input tree
+    /// are listed as I/O errors.
+    pub fn report(mut self, path: impl Into<PathBuf>, format: OutputFormat) -> Self {
+        self.report = Some((path.into(), format));
+        self
+    }
+
+    /// Serves Prometheus metrics on `http://<addr>/metrics` while the run progresses.
+    ///
+    /// See [`Metrics`] for what is exported; the listener closes when the run ends.
+    pub fn metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.metrics_addr = Some(addr);
+        self
+    }
+
+    /// Writes Prometheus metrics to `path` for the node-exporter textfile
+    /// collector, every `interval` and once more when the run ends.
+    pub fn metrics_textfile(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.metrics_textfile = Some(path.into());
+        self.metrics_interval = interval;
+        self
+    }
+
+    /// Stops dispatching files once `max` files have failed with an HTTP or I/O error.
+    ///
+    /// Files already in flight still finish; the remaining ones are left
+    /// untouched and [`BatchReport::aborted`] is set. `1` fails fast.
+    pub fn max_failures(mut self, max: usize) -> Self {
+        self.max_failures = Some(max.max(1));
+        self
+    }
+
+    /// Sets how thoroughly files are checked before they reach the backend.
+    ///
+    /// [`ValidationMode::Deep`] reads every sample first, so a file's
+    /// truncation, duration, silence, clipping and DC offset are checked
+    /// against [`BatchJob::validation_limits`]. Rejected files are skipped and
+    /// their reasons recorded in the journal.
+    pub fn validation(mut self, mode: ValidationMode) -> Self {
+        self.validation = mode;
+        self
+    }
+
+    /// Sets the thresholds used by [`ValidationMode::Deep`].
+    pub fn validation_limits(mut self, limits: ValidationLimits) -> Self {
+        self.validation_limits = limits;
+        self
+    }
+
+    /// Sets which files are skipped as already processed.
+    pub fn skip_policy(mut self, skip_policy: SkipPolicy) -> Self {
+        self.skip_policy = skip_policy;
+        self
+    }
+
+    /// Sets the journal location.
+    ///
+    /// Defaults to [`JOURNAL_FILE`] inside the output directory.
+    pub fn journal_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal_path = Some(path.into());
+        self
+    }
+
+    /// Caps the number of files processed concurrently.
+    ///
+    /// Defaults to the backend's capacity; for the HTTP client that is the
+    /// number of endpoints times the per-endpoint concurrency, and per-endpoint
+    /// limits are enforced by the client regardless.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+
+    /// Number of files processed concurrently.
+    pub fn concurrency(&self) -> usize {
+        let capacity = self.backend.capacity().max(1);
+        self.max_concurrency
+            .map_or(capacity, |max| max.clamp(1, capacity))
+    }
+
+    /// Backend that runs VAD on each file.
+    pub fn backend(&self) -> &dyn Backend {
+        &*self.backend
+    }
+
+    /// Walks the input directory and hands every supported audio file to the backend.
+    ///
+    /// The run first waits for the backend to become ready (e.g. for enough
+    /// healthy API endpoints) and keeps its background monitoring running.
+    ///
+    /// Every file's outcome is appended to the journal. Per-file failures are
+    /// logged to stderr and counted by kind in the [`BatchReport`]; only setup
+    /// errors (missing input directory, journal, thread pool creation) are returned.
+    pub fn run(&self) -> Result<BatchReport> {
+        let input_dir = self.canonical_input_dir()?;
+
+        // Ensure output directory exists
+        create_dir_all(&self.output_dir).with_context(|| {
+            format!(
+                "Failed to create output directory: {}",
+                self.output_dir.display()
+            )
+        })?;
+        let output_dir = self.output_dir.canonicalize().with_context(|| {
+            format!(
+                "Failed to find canonical path for output directory: {}",
+                self.output_dir.display()
+            )
+        })?;
+
+        let journal = Journal::open(self.journal_path_in(&output_dir))?;
+
+        let processed = AtomicUsize::new(0);
+        let already_done = AtomicUsize::new(0);
+        let invalid = AtomicUsize::new(0);
+        let http_errors = AtomicUsize::new(0);
+        let io_errors = AtomicUsize::new(0);
+        let aborted = AtomicBool::new(false);
+        // Segment count and speech seconds; records are not kept, so memory stays flat.
+        let speech = Mutex::new((0usize, 0.0f64));
+
+        let matcher = self.filter.build(&input_dir)?;
+        let progress = Progress::new();
+        let metrics = Metrics::new();
+        let listener = match self.metrics_addr {
+            Some(addr) => {
+                let listener = TcpListener::bind(addr)
+                    .and_then(|l| l.set_nonblocking(true).map(|()| l))
+                    .with_context(|| format!("Failed to listen for metrics on {addr}"))?;
+                info!(%addr, "Serving metrics on /metrics");
+                Some(listener)
+            }
+            None => None,
+        };
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(self.concurrency())
+            .build()
+            .context("Failed to create thread pool")?;
+
+        self.backend.wait_until_ready()?;
+
+        // Created last, so a failed setup leaves no unterminated report behind.
+        let report = self
+            .report
+            .as_ref()
+            .map(|(path, format)| RunReport::create(path, *format))
+            .transpose()?;
+
+        let (stop_health, health_stopped) = mpsc::channel::<()>();
+        let (stop_progress, progress_stopped) = mpsc::channel::<()>();
+        let (stop_listener, listener_stopped) = mpsc::channel::<()>();
+        let (stop_textfile, textfile_stopped) = mpsc::channel::<()>();
+        let walk_errors = thread::scope(|s| {
+            s.spawn(move || self.backend.monitor(&health_stopped));
+            let progress = &progress;
+            let backend = &*self.backend;
+            match self.progress.resolve() {
+                ProgressMode::Bar => {
+                    s.spawn(move || progress.draw_bar(backend, &progress_stopped));
+                }
+                ProgressMode::Log => {
+                    let interval = self.progress_interval;
+                    s.spawn(move || progress.report_every(backend, interval, &progress_stopped));
+                }
+                ProgressMode::Auto | ProgressMode::Off => {}
+            }
+            let metrics = &metrics;
+            if let Some(listener) = &listener {
+                s.spawn(move || metrics.serve(listener, progress, backend, &listener_stopped));
+            }
+            if let Some(path) = &self.metrics_textfile {
+                let interval = self.metrics_interval;
+                s.spawn(move || {
+                    metrics.export_every(path, interval, progress, backend, &textfile_stopped)
+                });
+            }
+
+            // The walker feeds a bounded queue so workers start right away and
+            // memory stays flat however large the tree is.
+            let (found, queue) = mpsc::sync_channel::<PathBuf>(DISCOVERY_QUEUE);
+            let walker = s.spawn(|| {
+                let errors = self.discover(&input_dir, &matcher, |path| {
+                    if aborted.load(Ordering::SeqCst) {
+                        return ControlFlow::Break(());
+                    }
+                    progress.discovered();
+                    // Only fails if the workers are gone, which ends the run anyway.
+                    let _ = found.send(path);
+                    ControlFlow::Continue(())
+                });
+                progress.walk_done();
+                drop(found);
+                errors
+            });
+
+            pool.install(|| {
+                queue.into_iter().par_bridge().for_each(|input_path| {
+                    // Keep draining the queue so the walker is never left blocked.
+                    if aborted.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let input_path = input_path.as_path();
+                    let relative = input_path.strip_prefix(&input_dir).unwrap_or(input_path);
+                    // Error level, so events keep their file at every verbosity.
+                    let _span = error_span!("file", input = %relative.display()).entered();
+                    let started = Instant::now();
+                    let mut file_metrics = FileMetrics::default();
+
+                    let (fingerprint, result) = match Fingerprint::of(input_path) {
+                        Ok(fp) => (
+                            Some(fp),
+                            self.process_file(
+                                input_path,
+                                relative,
+                                &output_dir,
+                                &journal,
+                                fp,
+                                &mut file_metrics,
+                            ),
+                        ),
+                        Err(e) => (None, Err(e)),
+                    };
+
+                    let record = report_record(relative, &result, &file_metrics);
+                    metrics.observe(&record);
+                    if let Some(report) = &report
+                        && let Err(e) = report.record(&record)
+                    {
+                        error!("{e:#}");
+                    }
+
+                    let audio = match &result {
+                        Ok(Outcome::Processed(record)) => {
+                            Duration::from_secs_f64(record.audio_secs)
+                        }
+                        _ => Duration::ZERO,
+                    };
+                    progress.finished(result.is_err(), audio);
+                    let (status, endpoint, error, rejections, speech) = match result {
+                        Ok(Outcome::Processed(record)) => {
+                            processed.fetch_add(1, Ordering::SeqCst);
+                            debug!(
+                                endpoint = %record.endpoint,
+                                segments = record.speech.map(|(segments, _)| segments),
+                                "Processed file"
+                            );
+                            if let Some((segments, seconds)) = record.speech {
+                                let mut speech = speech.lock().unwrap();
+                                speech.0 += segments;
+                                speech.1 += seconds;
+                            }
+                            (
+                                JournalStatus::Done,
+                                Some(record.endpoint),
+                                None,
+                                Vec::new(),
+                                record.speech,
+                            )
+                        }
+                        Ok(Outcome::AlreadyDone) => {
+                            already_done.fetch_add(1, Ordering::SeqCst);
+                            debug!("Skipping already processed file");
+                            // Journal files skipped for their existing output, so
+                            // a later `--resume` skips them too.
+                            if fingerprint.is_none_or(|fp| journal.is_settled(relative, fp)) {
+                                return;
+                            }
+                            (JournalStatus::Done, None, None, Vec::new(), None)
+                        }
+                        Ok(Outcome::Invalid(rejections)) => {
+                            invalid.fetch_add(1, Ordering::SeqCst);
+                            (JournalStatus::Invalid, None, None, rejections, None)
+                        }
+                        Err(e) => {
+                            error!(error = %format_args!("{e:#}"), "Failed to process file");
+                            match failure_status(&e) {
+                                FileStatus::HttpError => http_errors.fetch_add(1, Ordering::SeqCst),
+                                _ => io_errors.fetch_add(1, Ordering::SeqCst),
+                            };
+                            let failed = http_errors.load(Ordering::SeqCst)
+                                + io_errors.load(Ordering::SeqCst);
+                            if self.max_failures.is_some_and(|max| failed >= max)
+                                && !aborted.swap(true, Ordering::SeqCst)
+                            {
+                                error!(
+                                    failed,
+                                    "Failure threshold reached; no further files will be dispatched"
+                                );
+                            }
+                            let endpoint =
+                                e.downcast_ref::<RequestError>().map(|r| r.endpoint.clone());
+                            (
+                                JournalStatus::Failed,
+                                endpoint,
+                                Some(format!("{e:#}")),
+                                Vec::new(),
+                                None,
+                            )
+                        }
+                    };
+
+                    let Some(fingerprint) = fingerprint else {
+                        return;
+                    };
+                    let entry = JournalEntry {
+                        input: relative.to_path_buf(),
+                        status,
+                        fingerprint,
+                        endpoint,
+                        duration_ms: started.elapsed().as_millis() as u64,
+                        segments: speech.map(|(segments, _)| segments),
+                        speech_secs: speech.map(|(_, seconds)| seconds),
+                        error,
+                        rejections,
+                        finished_at: unix_now(),
+                    };
+                    if let Err(e) = journal.record(&entry) {
+                        error!("{e:#}");
+                    }
+                });
+            });
+
+            drop(stop_health);
+            drop(stop_progress);
+            drop(stop_listener);
+            drop(stop_textfile);
+            walker.join().expect("input walker panicked")
+        });
+
+        // Only succeeds once every converted copy has been cleaned up.
+        let _ = std::fs::remove_dir(output_dir.join(CONVERT_DIR));
+
+        if let Some(report) = report {
+            for error in &walk_errors {
+                let input = error.path.as_deref().map_or_else(PathBuf::new, |path| {
+                    path.strip_prefix(&input_dir).unwrap_or(path).to_path_buf()
+                });
+                let mut record = ReportRecord::new(input, FileStatus::IoError);
+                record.error = Some(error.error.clone());
+                report.record(&record)?;
+            }
+            report.finish(progress.elapsed())?;
+        }
+
+        let (segment_count, speech_seconds) = speech.into_inner().unwrap();
+        Ok(BatchReport {
+            processed: processed.load(Ordering::SeqCst),
+            already_done: already_done.load(Ordering::SeqCst),
+            invalid: invalid.load(Ordering::SeqCst),
+            http_errors: http_errors.load(Ordering::SeqCst),
+            io_errors: io_errors.load(Ordering::SeqCst),
+            aborted: aborted.load(Ordering::SeqCst),
+            segment_count,
+            speech_seconds,
+            walk_errors,
+        })
+    }
+
+    /// Walks the input directory and the validation a run would do, without
+    /// contacting the backend or writing anything.
+    ///
+    /// Files are classified under the configured skip policy, validation mode
+    /// and conversion settings. Audio durations come from the WAV header or
+    /// the container; deep validation reads every sample, as a run would.
+    pub fn plan(&self) -> Result<Plan> {
+        let input_dir = self.canonical_input_dir()?;
+        // The output directory may not exist yet, in which case nothing is done.
+        let output_dir = self
+            .output_dir
+            .canonicalize()
+            .or_else(|_| std::path::absolute(&self.output_dir))
+            .with_context(|| {
+                format!(
+                    "Failed to resolve output directory: {}",
+                    self.output_dir.display()
+                )
+            })?;
+        let journal = Journal::read_only(self.journal_path_in(&output_dir))?;
+
+        let matcher = self.filter.build(&input_dir)?;
+        let mut inputs = Vec::new();
+        let walk_errors = self.discover(&input_dir, &matcher, |path| {
+            inputs.push(path);
+            ControlFlow::Continue(())
+        });
+        let mut files: Vec<PlannedFile> = inputs
+            .par_iter()
+            .map(|input_path| {
+                let input_path = input_path.as_path();
+                let relative = input_path.strip_prefix(&input_dir).unwrap_or(input_path);
+                self.plan_file(input_path, relative, &output_dir, &journal)
+            })
+            .collect();
+        files.extend(walk_errors.into_iter().map(|e| PlannedFile {
+            input: e.path.as_deref().map_or_else(PathBuf::new, |path| {
+                path.strip_prefix(&input_dir).unwrap_or(path).to_path_buf()
+            }),
+            action: PlanAction::Unreadable,
+            duration_secs: None,
+            rejections: Vec::new(),
+            error: Some(e.error),
+        }));
+        files.sort_by(|a, b| a.input.cmp(&b.input));
+
+        Ok(Plan { files })
+    }
+
+    /// Classifies one file for [`BatchJob::plan`].
+    fn plan_file(
+        &self,
+        input_path: &Path,
+        relative: &Path,
+        output_dir: &Path,
+        journal: &Journal,
+    ) -> PlannedFile {
+        let mut planned = PlannedFile {
+            input: relative.to_path_buf(),
+            action: PlanAction::Unreadable,
+            duration_secs: None,
+            rejections: Vec::new(),
+            error: None,
+        };
+
+        let inspection = Fingerprint::of(input_path)
+            .and_then(|fp| self.inspect(input_path, relative, output_dir, journal, fp));
+        let duration = match inspection {
+            Ok(Inspection::AlreadyDone) => {
+                planned.action = PlanAction::AlreadyDone;
+                return planned;
+            }
+            Ok(Inspection::Invalid(rejections)) => {
+                planned.action = PlanAction::Invalid;
+                planned.rejections = rejections;
+                return planned;
+            }
+            Ok(Inspection::Ready(source)) => {
+                planned.action = match source {
+                    Source::Target { .. } => PlanAction::Process,
+                    _ => PlanAction::Convert,
+                };
+                match source {
+                    Source::Target { duration } | Source::Convert { duration } => Ok(duration),
+                    Source::Decoded(audio) => Ok(audio.duration()),
+                    Source::Compressed(decoder) => decoder.duration(input_path),
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        match duration {
+            Ok(duration) => planned.duration_secs = Some(duration.as_secs_f64()),
+            Err(e) => {
+                planned.action = PlanAction::Unreadable;
+                planned.error = Some(format!("{e:#}"));
+            }
+        }
+        planned
+    }
+
+    /// Canonical path of the input directory.
+    fn canonical_input_dir(&self) -> Result<PathBuf> {
+        // Resolve to absolute paths to avoid ambiguity
+        self.input_dir.canonicalize().with_context(|| {
+            format!(
+                "Failed to find canonical path for input directory: {}",
+                self.input_dir.display()
+            )
+        })
+    }
+
+    /// Journal location, defaulting to [`JOURNAL_FILE`] in `output_dir`.
+    fn journal_path_in(&self, output_dir: &Path) -> PathBuf {
+        self.journal_path
+            .clone()
+            .unwrap_or_else(|| output_dir.join(JOURNAL_FILE))
+    }
+
+    /// Lists the input files: the configured file list, or else the WAV files
+    /// and files the decoders handle below `input_dir`.
+    ///
+    /// Both are narrowed down by the [`PathFilter`]; excluded directories are
+    /// not descended into.
+    ///
+    /// Parts of the tree that cannot be read, including symlink loops, are
+    /// logged and returned alongside the files instead of aborting the walk.
+    ///
+    /// Files are handed to `emit` as they are found, so processing can start
+    /// before the walk finishes; the walk stops when `emit` breaks.
+    fn discover(
+        &self,
+        input_dir: &Path,
+        matcher: &PathMatcher,
+        mut emit: impl FnMut(PathBuf) -> ControlFlow<()>,
+    ) -> Vec<WalkError> {
+        if let Some(files) = &self.files {
+            let _ = resolve_listed(input_dir, files)
+                .into_iter()
+                .filter(|path| matcher.is_selected(path))
+                .try_for_each(emit);
+            return Vec::new();
+        }
+
+        let mut walker = WalkDir::new(input_dir).follow_links(self.walk.follow_symlinks);
+        if let Some(depth) = self.walk.max_depth {
+            walker = walker.max_depth(depth);
+        }
+
+        let mut errors = Vec::new();
+        let entries = walker.into_iter().filter_entry(|e| {
+            e.depth() == 0
+                || !((self.walk.skip_hidden && is_hidden(e.path()))
+                    || matcher.is_excluded(e.path(), e.file_type().is_dir()))
+        });
+        for entry in entries {
+            match entry {
+                Ok(entry) => {
+                    let path = entry.path();
+                    if entry.file_type().is_file()
+                        && (is_wav(path) || self.decoders.for_path(path).is_some())
+                        && matcher.is_selected(path)
+                        && emit(entry.into_path()).is_break()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let error = WalkError::from_walkdir(&e);
+                    warn!(%error, "Skipping unreadable input");
+                    errors.push(error);
+                }
+            }
+        }
+        errors
+    }
+
+    /// Whether a file counts as already processed under the skip policy.
+    fn is_already_done(
+        &self,
+        relative: &Path,
+        output_path: &Path,
+        journal: &Journal,
+        fingerprint: Fingerprint,
+    ) -> bool {
+        match self.skip_policy {
+            SkipPolicy::ExistingOutput => {
+                let input_name = relative.file_stem().unwrap_or_default();
+                output_path.join(input_name).exists()
+            }
+            SkipPolicy::Resume => journal.is_settled(relative, fingerprint),
+            SkipPolicy::Force => false,
+        }
+    }
+
+    /// Applies the skip policy and validation to a file, `relative` to the input directory.
+    fn inspect(
+        &self,
+        input_path: &Path,
+        relative: &Path,
+        output_dir: &Path,
+        journal: &Journal,
+        fingerprint: Fingerprint,
+    ) -> Result<Inspection<'_>> {
+        let output_path = output_dir.join(relative);
+        if self.is_already_done(relative, &output_path, journal, fingerprint) {
+            return Ok(Inspection::AlreadyDone);
+        }
+
+        let deep = self.validation == ValidationMode::Deep;
+        if let Some(decoder) = self.decoders.for_path(input_path) {
+            if !deep {
+                return Ok(Inspection::Ready(Source::Compressed(decoder)));
+            }
+            let audio = decoder.decode(input_path)?;
+            let rejections = SampleStats::of(&audio.samples).check(
+                audio.channels,
+                audio.sample_rate,
+                &self.validation_limits,
+            );
+            if !rejections.is_empty() {
+                return Ok(Inspection::Invalid(rejections));
+            }
+            return Ok(Inspection::Ready(Source::Decoded(audio)));
+        }
+
+        let (spec, duration) = wav_header(input_path)?;
+        let usable = is_target_format(&spec)
+            || needs_requantize_only(&spec)
+            || (self.convert && is_convertible(&spec));
+        if !usable {
+            return Ok(Inspection::Invalid(vec![Rejection::format(&spec)]));
+        }
+        if deep {
+            let rejections = deep_validate_wav(input_path, &self.validation_limits)?;
+            if !rejections.is_empty() {
+                return Ok(Inspection::Invalid(rejections));
+            }
+        }
+
+        if is_target_format(&spec) {
+            Ok(Inspection::Ready(Source::Target { duration }))
+        } else {
+            Ok(Inspection::Ready(Source::Convert { duration }))
+        }
+    }
+
+    /// Processes a single file, `relative` to the input directory.
+    fn process_file(
+        &self,
+        input_path: &Path,
+        relative: &Path,
+        output_dir: &Path,
+        journal: &Journal,
+        fingerprint: Fingerprint,
+        metrics: &mut FileMetrics,
+    ) -> Result<Outcome> {
+        let source = match self.inspect(input_path, relative, output_dir, journal, fingerprint)? {
+            Inspection::AlreadyDone => return Ok(Outcome::AlreadyDone),
+            Inspection::Invalid(rejections) => return Ok(reject(rejections)),
+            Inspection::Ready(source) => source,
+        };
+
+        let temp_root = output_dir.join(CONVERT_DIR);
+        let (converted, audio) = match source {
+            Source::Target { duration } => (None, duration),
+            Source::Convert { duration } => (
+                Some(ConvertedFile::create(
+                    input_path,
+                    &temp_root,
+                    &self.convert_options,
+                )?),
+                duration,
+            ),
+            Source::Decoded(audio) => (
+                Some(ConvertedFile::from_decoded(
+                    input_path,
+                    &audio,
+                    &temp_root,
+                    &self.convert_options,
+                )?),
+                audio.duration(),
+            ),
+            Source::Compressed(decoder) => {
+                let audio = decoder.decode(input_path)?;
+                (
+                    Some(ConvertedFile::from_decoded(
+                        input_path,
+                        &audio,
+                        &temp_root,
+                        &self.convert_options,
+                    )?),
+                    audio.duration(),
+                )
+            }
+        };
+        metrics.audio = Some(audio);
+        let output_path = output_dir.join(relative);
+        let source = converted.as_ref().map_or(input_path, |c| c.path());
+
+        if let Some(parent) = output_path.parent() {
+            create_dir_all(parent).with_context(|| {
+                format!(
+                    "Failed to create output directory for: {}",
+                    output_path.display()
+                )
+            })?;
+        }
+
+        let started = Instant::now();
+        let resp = self.backend.process(source, audio, &output_path);
+        metrics.latency = Some(started.elapsed());
+        let resp = resp?;
+
+        if let Some(status) = resp.status.filter(|&status| status != 200) {
+            return Err(RequestError {
+                endpoint: resp.endpoint,
+                status: Some(status),
+                message: format!("VAD failed: API returned status {status}"),
+            }
+            .into());
+        }
+
+        Ok(Outcome::Processed(FileRecord {
+            endpoint: resp.endpoint,
+            status: resp.status,
+            audio_secs: audio.as_secs_f64(),
+            speech: resp
+                .body
+                .map(|body| (body.segments.len(), body.speech_seconds())),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::{LocalVad, LocalVadConfig};
+    use crate::response::VadOutput;
+    use crate::test_util::TempDir;
+    use hound::{SampleFormat, WavSpec};
+
+    fn job(input: &TempDir, output: &TempDir) -> BatchJob {
+        BatchJob::new(
+            input.path(),
+            output.path(),
+            LocalVad::new(LocalVadConfig::default()),
+        )
+    }
+
+    /// One second of a 16 kHz 16-bit tone with `channels` channels.
+    fn tone(input: &TempDir, name: &str, channels: u16) -> PathBuf {
+        let spec = WavSpec {
+            channels,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let samples = (0..16_000 * usize::from(channels)).map(|n| {
+            let phase =
+                std::f32::consts::TAU * 440.0 * (n / usize::from(channels)) as f32 / 16_000.0;
+            (8_000.0 * phase.sin()) as i16
+        });
+        input.write_wav(name, spec, samples)
+    }
+
+    #[test]
+    fn processed_files_keep_segment_totals_in_journal_and_report() {
+        let (input, output) = (TempDir::new(), TempDir::new());
+        tone(&input, "a.wav", 1);
+        let report_path = output.path().join("report.json");
+
+        let report = job(&input, &output)
+            .report(&report_path, OutputFormat::Json)
+            .run()
+            .unwrap();
+        assert_eq!(report.segment_count, 1);
+        assert!(output.path().join("a.wav/a/segments.json").exists());
+
+        let journal = Journal::read_only(output.path().join(JOURNAL_FILE)).unwrap();
+        let entry = journal.get(Path::new("a.wav")).unwrap();
+        assert_eq!(entry.segments, Some(1));
+        assert!(entry.speech_secs.unwrap() > 0.9);
+
+        let report: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&report_path).unwrap()).unwrap();
+        assert_eq!(report["files"][0]["segments"], 1);
+    }
+
+    #[test]
+    fn mono_16khz_files_are_requantized_without_convert() {
+        let (input, output) = (TempDir::new(), TempDir::new());
+        let spec = |sample_rate, bits_per_sample, sample_format| WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        };
+        let wave = |rate: f32| {
+            (0..16_000).map(move |n| (std::f32::consts::TAU * 440.0 * n as f32 / rate).sin() / 4.0)
+        };
+        input.write_wav(
+            "s24.wav",
+            spec(16_000, 24, SampleFormat::Int),
+            wave(16_000.0).map(|s| (s * 8_388_607.0) as i32),
+        );
+        input.write_wav(
+            "f32.wav",
+            spec(16_000, 32, SampleFormat::Float),
+            wave(16_000.0),
+        );
+        input.write_wav(
+            "s24_48k.wav",
+            spec(48_000, 24, SampleFormat::Int),
+            wave(48_000.0).map(|s| (s * 8_388_607.0) as i32),
+        );
+
+        let report = job(&input, &output).run().unwrap();
+        assert_eq!((report.processed, report.invalid), (2, 1));
+        assert!(output.path().join("s24.wav/s24/segments.json").exists());
+        assert!(output.path().join("f32.wav/f32/segments.json").exists());
+        assert!(!output.path().join(CONVERT_DIR).exists());
+    }
+
+    /// A backend that never becomes ready.
+    #[derive(Debug)]
+    struct Unreachable;
+
+    impl Backend for Unreachable {
+        fn process(&self, _: &Path, _: Duration, _: &Path) -> Result<VadOutput> {
+            unreachable!("no file is dispatched before the backend is ready")
+        }
+
+        fn capacity(&self) -> usize {
+            1
+        }
+
+        fn wait_until_ready(&self) -> Result<()> {
+            anyhow::bail!("no endpoint is healthy")
+        }
+    }
+
+    #[test]
+    fn failed_setup_leaves_no_report() {
+        let (input, output) = (TempDir::new(), TempDir::new());
+        let report_path = output.path().join("report.json");
+        let result = BatchJob::new(input.path(), output.path(), Unreachable)
+            .report(&report_path, OutputFormat::Json)
+            .run();
+        assert!(result.is_err());
+        assert!(!report_path.exists());
+    }
+
+    #[test]
+    fn files_with_existing_output_are_journaled_for_resume() {
+        let (input, output) = (TempDir::new(), TempDir::new());
+        tone(&input, "a.wav", 1);
+        std::fs::create_dir_all(output.path().join("a.wav/a")).unwrap();
+
+        let report = job(&input, &output).run().unwrap();
+        assert_eq!((report.processed, report.already_done), (0, 1));
+
+        let report = job(&input, &output)
+            .skip_policy(SkipPolicy::Resume)
+            .run()
+            .unwrap();
+        assert_eq!((report.processed, report.already_done), (0, 1));
+    }
+
+    #[test]
+    fn resume_reinspects_files_rejected_as_invalid() {
+        let (input, output) = (TempDir::new(), TempDir::new());
+        tone(&input, "mono.wav", 1);
+        tone(&input, "stereo.wav", 2);
+
+        let report = job(&input, &output)
+            .skip_policy(SkipPolicy::Resume)
+            .run()
+            .unwrap();
+        assert_eq!((report.processed, report.invalid), (1, 1));
+
+        let report = job(&input, &output)
+            .skip_policy(SkipPolicy::Resume)
+            .convert(true)
+            .run()
+            .unwrap();
+        assert_eq!(
+            (report.processed, report.already_done, report.invalid),
+            (1, 1, 0)
+        );
+    }
+}
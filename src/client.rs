@@ -0,0 +1,481 @@
+use crate::backend::{Backend, EndpointStats, RequestError};
+use crate::endpoint::{CircuitBreaker, EndpointPool, Strategy};
+use crate::health::HealthCheck;
+use crate::response::{VadOutput, VadResponse};
+use crate::retry::RetryPolicy;
+use crate::timeout::TimeoutPolicy;
+use crate::upload::{self, RequestMode};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error_span, info, warn};
+use ureq::Body;
+use ureq::http::Response;
+
+/// JSON body sent to the VAD API for every input file.
+#[derive(Serialize, Debug, Clone)]
+pub struct VadRequestBody {
+    pub input_file: String,
+    pub output_dir: String,
+    pub model: Option<String>,
+}
+
+/// Request body prepared once per file and re-sent on every attempt.
+enum Payload {
+    Json(VadRequestBody),
+    Bytes {
+        content_type: String,
+        data: Vec<u8>,
+        model_header: Option<String>,
+    },
+}
+
+/// HTTP client that distributes VAD requests over one or more API endpoints.
+///
+/// Each request goes to the endpoint picked by the pool's [`Strategy`]
+/// (round-robin unless set with [`VadClient::with_strategy`]). Failed calls
+/// are retried according to a [`RetryPolicy`], each attempt bounded by a
+/// [`TimeoutPolicy`]. Connection errors and 5xx responses move the retry to a
+/// different endpoint and count towards that endpoint's [`CircuitBreaker`].
+/// The client is `Sync`, so a single instance can be shared between worker
+/// threads.
+#[derive(Debug)]
+pub struct VadClient {
+    agent: ureq::Agent,
+    endpoints: EndpointPool,
+    model: Option<String>,
+    retry: RetryPolicy,
+    timeouts: TimeoutPolicy,
+    health: Option<HealthCheck>,
+    mode: RequestMode,
+}
+
+impl VadClient {
+    /// Creates a client for the given API addresses.
+    ///
+    /// Addresses may carry a weight as `url=weight` or `url#weight` (see
+    /// [`parse_endpoint`](crate::endpoint::parse_endpoint)), used by
+    /// [`Strategy::Weighted`]. Fails if no address is provided or a weight is invalid.
+    pub fn new<I, S>(endpoints: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let endpoints: Vec<String> = endpoints.into_iter().map(Into::into).collect();
+        if endpoints.is_empty() {
+            anyhow::bail!("At least one API address must be provided");
+        }
+
+        let timeouts = TimeoutPolicy::default();
+        Ok(Self {
+            agent: agent_with(&timeouts),
+            endpoints: EndpointPool::new(endpoints)?,
+            model: None,
+            retry: RetryPolicy::default(),
+            timeouts,
+            health: None,
+            mode: RequestMode::default(),
+        })
+    }
+
+    /// Sets the model name forwarded to the VAD API.
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Sets how audio is handed to the VAD server.
+    pub fn with_request_mode(mut self, mode: RequestMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// How audio is handed to the VAD server.
+    pub fn request_mode(&self) -> RequestMode {
+        self.mode
+    }
+
+    /// Sets the policy used to retry failed API calls.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the connect and request timeouts of API calls.
+    pub fn with_timeouts(mut self, timeouts: TimeoutPolicy) -> Self {
+        self.agent = agent_with(&timeouts);
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Sets how many requests each endpoint may have in flight at once.
+    pub fn with_concurrency_per_endpoint(mut self, concurrency: usize) -> Self {
+        self.endpoints = self.endpoints.with_max_in_flight(concurrency);
+        self
+    }
+
+    /// Sets how endpoints are chosen for each request.
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.endpoints = self.endpoints.with_strategy(strategy);
+        self
+    }
+
+    /// Sets when failing endpoints are ejected from the rotation.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.endpoints = self.endpoints.with_breaker(breaker);
+        self
+    }
+
+    /// Enables health probing of the endpoints.
+    pub fn with_health_check(mut self, health: HealthCheck) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Endpoints this client sends requests to.
+    pub fn endpoints(&self) -> &EndpointPool {
+        &self.endpoints
+    }
+
+    /// Model name forwarded to the VAD API, if any.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Policy used to retry failed API calls.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Connect and request timeouts of API calls.
+    pub fn timeouts(&self) -> &TimeoutPolicy {
+        &self.timeouts
+    }
+
+    /// Health probing settings, if enabled.
+    pub fn health_check(&self) -> Option<&HealthCheck> {
+        self.health.as_ref()
+    }
+
+    /// Probes every endpoint concurrently and updates its health flag.
+    ///
+    /// Returns the number of healthy endpoints. Without a configured health
+    /// check all endpoints are considered healthy.
+    pub fn probe_endpoints(&self) -> usize {
+        let Some(health) = &self.health else {
+            return self.endpoints.len();
+        };
+
+        thread::scope(|s| {
+            for endpoint in self.endpoints.iter() {
+                s.spawn(move || {
+                    let healthy = health.probe(&self.agent, endpoint.url());
+                    if endpoint.set_healthy(healthy) {
+                        if healthy {
+                            info!(endpoint = endpoint.url(), "Endpoint is now healthy");
+                        } else {
+                            warn!(endpoint = endpoint.url(), "Endpoint is now unhealthy");
+                        }
+                    }
+                });
+            }
+        });
+
+        self.endpoints.iter().filter(|e| e.is_healthy()).count()
+    }
+
+    /// Blocks until the configured number of endpoints report healthy.
+    ///
+    /// Fails if they are not ready within the health check's `ready_timeout`.
+    pub fn wait_until_ready(&self) -> Result<()> {
+        let Some(health) = &self.health else {
+            return Ok(());
+        };
+        let required = health.min_ready.min(self.endpoints.len());
+        let deadline = Instant::now() + health.ready_timeout;
+
+        loop {
+            let ready = self.probe_endpoints();
+            if ready >= required {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                anyhow::bail!(
+                    "Only {ready} of {required} required API endpoints became ready within {:?}",
+                    health.ready_timeout
+                );
+            }
+            info!(ready, required, "Waiting for API endpoints");
+            thread::sleep(
+                health
+                    .interval
+                    .min(deadline.saturating_duration_since(Instant::now())),
+            );
+        }
+    }
+
+    /// Re-probes the endpoints every health check interval until `stop` is
+    /// signalled or its sender is dropped.
+    pub fn monitor_health(&self, stop: &Receiver<()>) {
+        let Some(health) = &self.health else {
+            return;
+        };
+
+        while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(health.interval) {
+            self.probe_endpoints();
+        }
+    }
+
+    /// Builds the request body for `input_file` according to the request mode.
+    fn payload(&self, input_file: &Path, output_dir: &Path) -> Result<Payload> {
+        if !self.mode.is_upload() {
+            return Ok(Payload::Json(VadRequestBody {
+                input_file: input_file.to_string_lossy().to_string(),
+                output_dir: output_dir.to_string_lossy().to_string(),
+                model: self.model.clone(),
+            }));
+        }
+
+        let audio = fs::read(input_file)
+            .with_context(|| format!("Failed to read: {}", input_file.display()))?;
+
+        Ok(match self.mode {
+            RequestMode::Multipart => {
+                let file_name = input_file.file_name().unwrap_or_default().to_string_lossy();
+                let (content_type, data) =
+                    upload::multipart_body(&file_name, &audio, self.model.as_deref());
+                Payload::Bytes {
+                    content_type,
+                    data,
+                    model_header: None,
+                }
+            }
+            _ => Payload::Bytes {
+                content_type: "audio/wav".to_string(),
+                data: audio,
+                model_header: self.model.clone(),
+            },
+        })
+    }
+
+    fn send(
+        &self,
+        api_addr: &str,
+        payload: &Payload,
+        budget: Option<Duration>,
+    ) -> Result<Response<Body>, ureq::Error> {
+        let req = self
+            .agent
+            .post(api_addr)
+            .config()
+            .timeout_global(budget)
+            .build();
+        match payload {
+            Payload::Json(body) => req.send_json(body),
+            Payload::Bytes {
+                content_type,
+                data,
+                model_header,
+            } => {
+                let mut req = req.header("Content-Type", content_type);
+                if let Some(model) = model_header {
+                    req = req.header("X-Vad-Model", model);
+                }
+                req.send(&data[..])
+            }
+        }
+    }
+
+    /// Asks an endpoint to run VAD on `input_file`, holding `audio` of audio,
+    /// writing results into `output_dir`.
+    ///
+    /// In upload modes the audio is sent in the request and the returned
+    /// speech audio or segments are written to `output_dir/<file stem>/`.
+    /// Retryable failures are retried with backoff until the policy's attempt
+    /// budget is exhausted, preferring endpoints not yet tried for this file.
+    /// Every attempt gets the full request timeout, scaled by `audio` if the
+    /// timeout policy says so.
+    /// Returns the response status and, for JSON responses, the parsed
+    /// [`VadResponse`]. In shared-path mode, output files listed in the
+    /// response must exist afterwards, and the parsed response is also saved
+    /// as `output_dir/<file stem>/segments.json`.
+    pub fn process(
+        &self,
+        input_file: &Path,
+        audio: Duration,
+        output_dir: &Path,
+    ) -> Result<VadOutput> {
+        let payload = self.payload(input_file, output_dir)?;
+        let budget = self.timeouts.request_budget(Some(audio));
+        debug!(?budget, "Request timeout");
+
+        let mut tried = Vec::new();
+        let mut attempt = 1;
+        loop {
+            let lease = match self.endpoints.acquire(&tried) {
+                Some(lease) => lease,
+                None => {
+                    // Every endpoint has been tried once; start another round.
+                    tried.clear();
+                    self.endpoints.acquire(&tried).unwrap()
+                }
+            };
+            let idx = lease.idx();
+            let api_addr = self.endpoints.get(idx).url();
+            let _span = error_span!("request", endpoint = api_addr, attempt).entered();
+
+            let started = Instant::now();
+            let result = self.send(api_addr, &payload, budget);
+
+            match &result {
+                Err(e) if is_endpoint_failure(e) => self.endpoints.record_failure(idx),
+                _ => {
+                    self.endpoints.record_latency(idx, started.elapsed());
+                    self.endpoints.record_success(idx);
+                }
+            }
+
+            match result {
+                Ok(mut resp) => {
+                    let body = if self.mode.is_upload() {
+                        let dir = output_dir.join(input_file.file_stem().unwrap_or_default());
+                        upload::write_response(&mut resp, &dir)?.1
+                    } else {
+                        let body = read_json_body(&mut resp)?;
+                        if let Some(body) = &body {
+                            verify_output_files(body)?;
+                            let dir = output_dir.join(input_file.file_stem().unwrap_or_default());
+                            upload::write_segments(&dir, body)?;
+                        }
+                        body
+                    };
+                    return Ok(VadOutput {
+                        endpoint: api_addr.to_string(),
+                        status: Some(resp.status().as_u16()),
+                        body,
+                    });
+                }
+                Err(e) if attempt < self.retry.max_attempts && self.retry.is_retryable(&e) => {
+                    drop(lease);
+                    let delay = self.retry.delay_for(attempt);
+                    warn!(error = %e, retry_in = ?delay, "Request failed, retrying");
+                    if is_endpoint_failure(&e) {
+                        tried.push(idx);
+                    }
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    let status = match &e {
+                        ureq::Error::StatusCode(code) => Some(*code),
+                        _ => None,
+                    };
+                    return Err(anyhow::Error::new(e).context(RequestError {
+                        endpoint: api_addr.to_string(),
+                        status,
+                        message: format!(
+                            "VAD request to {api_addr} failed after {attempt} attempt(s)"
+                        ),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+impl Backend for VadClient {
+    fn process(&self, input_file: &Path, audio: Duration, output_dir: &Path) -> Result<VadOutput> {
+        VadClient::process(self, input_file, audio, output_dir)
+    }
+
+    fn capacity(&self) -> usize {
+        self.endpoints.capacity()
+    }
+
+    fn wait_until_ready(&self) -> Result<()> {
+        VadClient::wait_until_ready(self)
+    }
+
+    fn monitor(&self, stop: &Receiver<()>) {
+        self.monitor_health(stop);
+    }
+
+    fn endpoint_stats(&self) -> Vec<EndpointStats> {
+        self.endpoints
+            .iter()
+            .enumerate()
+            .map(|(idx, endpoint)| EndpointStats {
+                name: endpoint.url().to_string(),
+                in_flight: self.endpoints.in_flight(idx),
+                latency: endpoint.latency(),
+                available: endpoint.is_healthy() && !endpoint.is_ejected(),
+            })
+            .collect()
+    }
+}
+
+/// Checks that output files a shared-path server claims to have written exist.
+fn verify_output_files(body: &VadResponse) -> Result<()> {
+    let missing: Vec<_> = body
+        .output_files
+        .iter()
+        .filter(|f| !Path::new(f).exists())
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "VAD API reported {} output file(s) that do not exist, e.g. {}",
+            missing.len(),
+            missing[0]
+        );
+    }
+    Ok(())
+}
+
+/// Parses a JSON response body. Empty and non-JSON bodies yield `None`.
+fn read_json_body(resp: &mut Response<Body>) -> Result<Option<VadResponse>> {
+    let body = resp.body_mut();
+    let is_json = body
+        .mime_type()
+        .is_some_and(|mime| mime == "application/json" || mime.ends_with("+json"));
+    if !is_json {
+        return Ok(None);
+    }
+
+    let data = body
+        .with_config()
+        .limit(u64::MAX)
+        .read_to_vec()
+        .context("Failed to read VAD API response body")?;
+    if data.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+
+    let parsed = serde_json::from_slice(&data).context("Invalid VAD API response JSON")?;
+    Ok(Some(parsed))
+}
+
+/// HTTP agent enforcing the connect timeout of `timeouts`; request
+/// timeouts are set per call, as they may depend on the audio.
+fn agent_with(timeouts: &TimeoutPolicy) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_connect(timeouts.connect)
+        .build()
+        .new_agent()
+}
+
+/// Whether an error points at a broken endpoint rather than a bad request.
+fn is_endpoint_failure(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::StatusCode(status) => *status >= 500,
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed
+        | ureq::Error::Protocol(_) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,375 @@
+use crate::decode::DecodedAudio;
+use crate::wav::{SourceFormat, TARGET_SAMPLE_RATE};
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Zero crossings of the sinc kernel on each side, at the output sample rate.
+const SINC_ZERO_CROSSINGS: usize = 16;
+/// Kaiser window shape; ~80 dB stop-band attenuation.
+const KAISER_BETA: f64 = 8.0;
+/// Pass-band edge as a fraction of the lower Nyquist frequency.
+const ROLLOFF: f64 = 0.95;
+
+/// Name of the scratch directory for converted files, created in the output directory.
+pub const CONVERT_DIR: &str = ".vad-convert";
+
+static NEXT_TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Options for converting inputs to the target format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConvertOptions {
+    /// Add triangular (TPDF) dither of ±1 LSB before requantizing to 16 bits.
+    pub dither: bool,
+}
+
+/// Whether a file with this header can be converted to the target format.
+pub fn is_convertible(spec: &WavSpec) -> bool {
+    spec.channels > 0 && spec.sample_rate > 0 && SourceFormat::of(spec).is_supported()
+}
+
+/// Whether a file with this header is 16 kHz mono and only needs its samples
+/// requantized to 16 bits, which is done even without `--convert`.
+pub fn needs_requantize_only(spec: &WavSpec) -> bool {
+    spec.channels == 1
+        && spec.sample_rate == TARGET_SAMPLE_RATE
+        && is_convertible(spec)
+        && SourceFormat::of(spec) != SourceFormat::Pcm16
+}
+
+/// Reads a WAV file and converts it to 16 kHz mono 16-bit PCM.
+///
+/// 8/16/24/32-bit integer and 32-bit float samples are decoded to floats,
+/// channels are averaged, the signal is resampled with a Kaiser-windowed sinc
+/// polyphase filter, and requantized with rounding and clipping.
+pub fn convert_to_target(path: &Path, options: &ConvertOptions) -> Result<Vec<i16>> {
+    let mut reader = WavReader::open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    let spec = reader.spec();
+    if !is_convertible(&spec) {
+        anyhow::bail!(
+            "Unsupported WAV format for conversion: {} channel(s), {}",
+            spec.channels,
+            SourceFormat::of(&spec)
+        );
+    }
+
+    let samples = match SourceFormat::of(&spec) {
+        SourceFormat::Float32 => reader.samples::<f32>().collect::<Result<Vec<_>, _>>(),
+        _ => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect()
+        }
+    }
+    .with_context(|| format!("Failed to read samples: {}", path.display()))?;
+
+    Ok(convert_samples(
+        &samples,
+        spec.channels,
+        spec.sample_rate,
+        options,
+    ))
+}
+
+/// Converts interleaved samples in `-1.0..=1.0` to 16 kHz mono 16-bit PCM.
+pub fn convert_samples(
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    options: &ConvertOptions,
+) -> Vec<i16> {
+    let mono = downmix(samples, channels);
+    let resampled = resample(&mono, sample_rate, TARGET_SAMPLE_RATE);
+    to_pcm16(&resampled, options.dither)
+}
+
+/// Averages interleaved channels into a single channel.
+pub fn downmix(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = usize::from(channels.max(1));
+    if channels == 1 {
+        return samples.to_vec();
+    }
+
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Requantizes samples in `-1.0..=1.0` to 16-bit PCM with rounding and clipping,
+/// optionally adding TPDF dither.
+pub fn to_pcm16(samples: &[f32], dither: bool) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|&s| {
+            let noise = if dither {
+                fastrand::f32() - fastrand::f32()
+            } else {
+                0.0
+            };
+            (s * 32768.0 + noise).round().clamp(-32768.0, 32767.0) as i16
+        })
+        .collect()
+}
+
+/// Resamples `input` from `from` Hz to `to` Hz with a windowed-sinc polyphase filter.
+///
+/// The ratio is reduced to `up / down`; a bank of `up` filter phases is
+/// precomputed and each output sample is the dot product of one phase with
+/// the surrounding input samples. The cutoff sits just below the lower of
+/// the two Nyquist frequencies, so downsampling is anti-aliased.
+pub fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || input.is_empty() {
+        return input.to_vec();
+    }
+
+    let g = gcd(from, to);
+    let up = (to / g) as usize;
+    let down = (from / g) as usize;
+
+    // Cutoff relative to the input Nyquist frequency.
+    let cutoff = (f64::from(to) / f64::from(from)).min(1.0) * ROLLOFF;
+    let half = (SINC_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+    let taps = 2 * half;
+
+    let bank: Vec<Vec<f32>> = (0..up)
+        .map(|phase| {
+            let frac = phase as f64 / up as f64;
+            let mut kernel: Vec<f64> = (0..taps)
+                .map(|j| {
+                    let x = (j as f64 - half as f64 + 1.0) - frac;
+                    cutoff * sinc(cutoff * x) * kaiser(x / half as f64)
+                })
+                .collect();
+            let sum: f64 = kernel.iter().sum();
+            if sum.abs() > f64::EPSILON {
+                kernel.iter_mut().for_each(|k| *k /= sum);
+            }
+            kernel.into_iter().map(|k| k as f32).collect()
+        })
+        .collect();
+
+    let out_len = (input.len() * up).div_ceil(down);
+    (0..out_len)
+        .map(|n| {
+            let pos = n * down;
+            let base = pos / up;
+            let kernel = &bank[pos % up];
+            let first = base as isize - half as isize + 1;
+
+            kernel
+                .iter()
+                .enumerate()
+                .filter_map(|(j, &k)| {
+                    let idx = first + j as isize;
+                    (idx >= 0 && (idx as usize) < input.len()).then(|| input[idx as usize] * k)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Kaiser window over `-1.0..=1.0`.
+fn kaiser(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    bessel_i0(KAISER_BETA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_BETA)
+}
+
+/// Zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= (half_x / k as f64).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+/// A converted copy of an input file, removed when dropped.
+///
+/// The copy keeps the original file name so that backends deriving output
+/// names from it produce the same layout as for unconverted inputs.
+#[derive(Debug)]
+pub struct ConvertedFile {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl ConvertedFile {
+    /// Converts `input` and writes the result below `temp_root`.
+    pub fn create(input: &Path, temp_root: &Path, options: &ConvertOptions) -> Result<Self> {
+        let samples = convert_to_target(input, options)?;
+        Self::write(input, temp_root, &samples)
+    }
+
+    /// Converts audio decoded from `input` and writes the result below `temp_root`.
+    pub fn from_decoded(
+        input: &Path,
+        audio: &DecodedAudio,
+        temp_root: &Path,
+        options: &ConvertOptions,
+    ) -> Result<Self> {
+        if audio.channels == 0 || audio.sample_rate == 0 {
+            anyhow::bail!(
+                "Decoded audio has no channels or sample rate: {}",
+                input.display()
+            );
+        }
+        let samples = convert_samples(&audio.samples, audio.channels, audio.sample_rate, options);
+        Self::write(input, temp_root, &samples)
+    }
+
+    /// Writes `samples` as a 16 kHz mono WAV named after `input`.
+    fn write(input: &Path, temp_root: &Path, samples: &[i16]) -> Result<Self> {
+        let id = NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = temp_root.join(format!("{}-{id}", process::id()));
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        let converted = Self {
+            // Keep the stem, which backends use to name their outputs.
+            path: dir
+                .join(input.file_name().unwrap_or_default())
+                .with_extension("wav"),
+            dir,
+        };
+
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: TARGET_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(&converted.path, spec)
+            .with_context(|| format!("Failed to create: {}", converted.path.display()))?;
+        for &sample in samples {
+            writer.write_sample(sample)?;
+        }
+        writer
+            .finalize()
+            .with_context(|| format!("Failed to write: {}", converted.path.display()))?;
+
+        Ok(converted)
+    }
+
+    /// Location of the converted file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ConvertedFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn tone(freq: f32, rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|n| 0.5 * (TAU * freq * n as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    /// RMS level away from the edges, where the filter runs off the input.
+    fn inner_rms(samples: &[f32]) -> f32 {
+        let inner = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        (inner.iter().map(|s| s * s).sum::<f32>() / inner.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn resample_output_length_follows_ratio() {
+        assert_eq!(resample(&vec![0.0; 48_000], 48_000, 16_000).len(), 16_000);
+        assert_eq!(resample(&vec![0.0; 44_100], 44_100, 16_000).len(), 16_000);
+        assert_eq!(resample(&vec![0.0; 8_000], 8_000, 16_000).len(), 16_000);
+        assert_eq!(resample(&[0.25; 10], 16_000, 16_000), vec![0.25; 10]);
+    }
+
+    #[test]
+    fn resample_has_unit_dc_gain() {
+        for from in [48_000, 44_100, 22_050, 8_000] {
+            let out = resample(&vec![0.5; from as usize], from, 16_000);
+            let inner = &out[out.len() / 4..out.len() * 3 / 4];
+            assert!(
+                inner.iter().all(|s| (s - 0.5).abs() < 1e-3),
+                "DC gain off for {from} Hz"
+            );
+        }
+    }
+
+    #[test]
+    fn resample_keeps_passband_tone() {
+        let out = resample(&tone(1_000.0, 48_000, 48_000), 48_000, 16_000);
+        let expected = 0.5 / 2f32.sqrt();
+        assert!((inner_rms(&out) - expected).abs() < 0.01 * expected);
+    }
+
+    #[test]
+    fn resample_attenuates_tone_above_target_nyquist() {
+        for (freq, from) in [(10_000.0, 48_000), (12_000.0, 44_100)] {
+            let out = resample(&tone(freq, from, from as usize), from, 16_000);
+            // At least 60 dB below the 0.35 RMS input.
+            assert!(inner_rms(&out) < 0.35e-3, "{freq} Hz leaked from {from} Hz");
+        }
+    }
+
+    #[test]
+    fn downmix_averages_channels() {
+        assert_eq!(downmix(&[0.25, 0.75, -1.0, 1.0], 2), vec![0.5, 0.0]);
+        assert_eq!(downmix(&[0.25, 0.5, 0.75], 3), vec![0.5]);
+        assert_eq!(downmix(&[0.1, 0.2], 1), vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn to_pcm16_rounds_and_clips() {
+        assert_eq!(
+            to_pcm16(&[0.0, 0.5, -0.5, 1.0, -1.0, 1.5, -1.5], false),
+            vec![0, 16384, -16384, 32767, -32768, 32767, -32768]
+        );
+    }
+
+    #[test]
+    fn to_pcm16_dither_stays_within_one_step() {
+        let plain = to_pcm16(&[0.25; 1000], false);
+        let dithered = to_pcm16(&[0.25; 1000], true);
+        assert!(
+            plain
+                .iter()
+                .zip(&dithered)
+                .all(|(a, b)| (i32::from(*a) - i32::from(*b)).abs() <= 1)
+        );
+        assert_eq!(to_pcm16(&[1.0; 100], true), vec![32767; 100]);
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::codecs::audio::well_known::CODEC_ID_OPUS;
+use symphonia::core::codecs::audio::{AudioCodecParameters, AudioDecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::probe::Hint;
+use symphonia::core::formats::{FormatOptions, FormatReader, TrackType};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+
+/// Sample rate Opus streams are decoded at; Opus decoders can output it natively.
+const OPUS_DECODE_RATE: u32 = 16000;
+/// Rate Ogg Opus pre-skip values are expressed in.
+const OPUS_GRANULE_RATE: u32 = 48000;
+
+/// Audio decoded from a compressed file, as interleaved samples in `-1.0..=1.0`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl DecodedAudio {
+    /// Playback duration of the samples.
+    pub fn duration(&self) -> Duration {
+        if self.channels == 0 || self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        let frames = self.samples.len() / usize::from(self.channels);
+        Duration::from_secs_f64(frames as f64 / f64::from(self.sample_rate))
+    }
+}
+
+/// Decodes one family of non-WAV input files.
+///
+/// Decoded audio is converted to 16 kHz mono 16-bit PCM and handed to the
+/// backend like any other input; outputs keep the input's relative path.
+pub trait Decoder: Send + Sync + fmt::Debug {
+    /// Lower-case file extensions (without the dot) this decoder handles.
+    fn extensions(&self) -> &[&'static str];
+
+    /// Decodes the whole file at `path`.
+    fn decode(&self, path: &Path) -> Result<DecodedAudio>;
+
+    /// Playback duration of the file at `path`.
+    ///
+    /// Defaults to decoding the whole file; implementations should read it
+    /// from the container when they can.
+    fn duration(&self, path: &Path) -> Result<Duration> {
+        let audio = self.decode(path)?;
+        Ok(audio.duration())
+    }
+}
+
+/// Pure-Rust decoder for FLAC, MP3, Ogg Vorbis and Ogg Opus files.
+///
+/// Containers and the FLAC/MP3/Vorbis codecs are handled by `symphonia`;
+/// Opus packets are decoded with `opus-decoder`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymphoniaDecoder;
+
+impl Decoder for SymphoniaDecoder {
+    fn extensions(&self) -> &[&'static str] {
+        &["flac", "mp3", "ogg", "oga", "opus"]
+    }
+
+    fn decode(&self, path: &Path) -> Result<DecodedAudio> {
+        let (mut format, track) = open_track(path)?;
+        let AudioTrack {
+            id: track_id,
+            params,
+            delay,
+            ..
+        } = track;
+
+        if params.codec == CODEC_ID_OPUS {
+            let channels = params.channels.as_ref().map_or(1, |c| c.count());
+            return decode_opus(&mut *format, track_id, channels, delay)
+                .with_context(|| format!("Failed to decode Opus stream: {}", path.display()));
+        }
+
+        let mut decoder = symphonia::default::get_codecs()
+            .make_audio_decoder(&params, &AudioDecoderOptions::default())
+            .with_context(|| format!("Unsupported codec in: {}", path.display()))?;
+
+        let mut audio = DecodedAudio::default();
+        let mut buf = Vec::new();
+        while let Some(packet) = next_packet(&mut *format)? {
+            if packet.track_id != track_id {
+                continue;
+            }
+            match decoder.decode(&packet) {
+                Ok(decoded) => {
+                    audio.sample_rate = decoded.spec().rate();
+                    audio.channels = decoded.spec().channels().count() as u16;
+                    buf.resize(decoded.samples_interleaved(), 0.0f32);
+                    decoded.copy_to_slice_interleaved(&mut buf);
+                    audio.samples.extend_from_slice(&buf);
+                }
+                // Corrupt packets are skipped, as players do.
+                Err(SymphoniaError::DecodeError(_)) => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to decode: {}", path.display()));
+                }
+            }
+        }
+
+        if audio.sample_rate == 0 {
+            anyhow::bail!("No audio decoded from: {}", path.display());
+        }
+        Ok(audio)
+    }
+
+    fn duration(&self, path: &Path) -> Result<Duration> {
+        let (_, track) = open_track(path)?;
+        match (track.num_frames, track.params.sample_rate) {
+            (Some(frames), Some(rate)) if rate > 0 => {
+                Ok(Duration::from_secs_f64(frames as f64 / f64::from(rate)))
+            }
+            // E.g. MP3 files without a Xing/VBRI header.
+            _ => Ok(self.decode(path)?.duration()),
+        }
+    }
+}
+
+/// Default audio track of a probed container.
+struct AudioTrack {
+    id: u32,
+    params: AudioCodecParameters,
+    /// Encoder delay in frames, at the track's sample rate.
+    delay: u32,
+    /// Playable frames, if the container records them.
+    num_frames: Option<u64>,
+}
+
+/// Probes the container at `path` and picks its default audio track.
+fn open_track(path: &Path) -> Result<(Box<dyn FormatReader>, AudioTrack)> {
+    let file = File::open(path).with_context(|| format!("Failed to open: {}", path.display()))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let format = symphonia::default::get_probe()
+        .probe(
+            &hint,
+            mss,
+            FormatOptions::default(),
+            MetadataOptions::default(),
+        )
+        .with_context(|| format!("Unsupported audio container: {}", path.display()))?;
+
+    let track = format
+        .default_track(TrackType::Audio)
+        .with_context(|| format!("No audio track in: {}", path.display()))?;
+    let params = track
+        .codec_params
+        .as_ref()
+        .and_then(|p| p.audio())
+        .with_context(|| format!("Missing codec parameters in: {}", path.display()))?
+        .clone();
+    let track = AudioTrack {
+        id: track.id,
+        params,
+        delay: track.delay.unwrap_or(0),
+        num_frames: track.num_frames,
+    };
+
+    Ok((format, track))
+}
+
+/// Reads the next packet, treating end of stream as `None`.
+fn next_packet(format: &mut dyn FormatReader) -> Result<Option<symphonia::core::packet::Packet>> {
+    match format.next_packet() {
+        Ok(packet) => Ok(packet),
+        Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Ok(None)
+        }
+        // Chained Ogg streams: only the first logical stream is decoded.
+        Err(SymphoniaError::ResetRequired) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Decodes an Opus track directly to mono at [`OPUS_DECODE_RATE`], dropping the pre-skip.
+fn decode_opus(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    channels: usize,
+    pre_skip: u32,
+) -> Result<DecodedAudio> {
+    if channels > 2 {
+        anyhow::bail!("Multichannel Opus streams ({channels} channels) are not supported");
+    }
+
+    let mut decoder = opus_decoder::OpusDecoder::new(OPUS_DECODE_RATE, 1)
+        .map_err(|e| anyhow::anyhow!("Failed to create Opus decoder: {e}"))?;
+    let mut pcm = vec![0f32; decoder.max_frame_size_per_channel()];
+    let mut samples = Vec::new();
+
+    while let Some(packet) = next_packet(format)? {
+        if packet.track_id != track_id {
+            continue;
+        }
+        let n = decoder
+            .decode_float(&packet.data, &mut pcm, false)
+            .map_err(|e| anyhow::anyhow!("Invalid Opus packet: {e}"))?;
+        samples.extend_from_slice(&pcm[..n]);
+    }
+
+    let skip = (pre_skip as usize * OPUS_DECODE_RATE as usize / OPUS_GRANULE_RATE as usize)
+        .min(samples.len());
+    samples.drain(..skip);
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: OPUS_DECODE_RATE,
+        channels: 1,
+    })
+}
+
+/// Set of decoders consulted by file extension.
+#[derive(Debug, Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<Box<dyn Decoder>>,
+}
+
+impl DecoderRegistry {
+    /// A registry with no decoders; only WAV files are processed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the built-in [`SymphoniaDecoder`].
+    pub fn with_defaults() -> Self {
+        Self::new().with(SymphoniaDecoder)
+    }
+
+    /// Adds a decoder. Earlier decoders win when extensions overlap.
+    pub fn with(mut self, decoder: impl Decoder + 'static) -> Self {
+        self.decoders.push(Box::new(decoder));
+        self
+    }
+
+    /// Keeps only the given extensions, dropping decoders left without any.
+    pub fn restrict_to(self, extensions: &[String]) -> Self {
+        let decoders = self
+            .decoders
+            .into_iter()
+            .map(|decoder| -> Box<dyn Decoder> {
+                let kept: Vec<&'static str> = decoder
+                    .extensions()
+                    .iter()
+                    .copied()
+                    .filter(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+                    .collect();
+                Box::new(Restricted {
+                    inner: decoder,
+                    extensions: kept,
+                })
+            })
+            .filter(|decoder| !decoder.extensions().is_empty())
+            .collect();
+        Self { decoders }
+    }
+
+    /// All extensions handled by the registered decoders.
+    pub fn extensions(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.decoders
+            .iter()
+            .flat_map(|d| d.extensions().iter().copied())
+    }
+
+    /// Decoder responsible for `path`, by its extension.
+    pub fn for_path(&self, path: &Path) -> Option<&dyn Decoder> {
+        let ext = path.extension()?.to_str()?;
+        self.decoders
+            .iter()
+            .find(|d| d.extensions().iter().any(|e| e.eq_ignore_ascii_case(ext)))
+            .map(|d| &**d)
+    }
+}
+
+/// A decoder limited to a subset of its extensions.
+#[derive(Debug)]
+struct Restricted {
+    inner: Box<dyn Decoder>,
+    extensions: Vec<&'static str>,
+}
+
+impl Decoder for Restricted {
+    fn extensions(&self) -> &[&'static str] {
+        &self.extensions
+    }
+
+    fn decode(&self, path: &Path) -> Result<DecodedAudio> {
+        self.inner.decode(path)
+    }
+
+    fn duration(&self, path: &Path) -> Result<Duration> {
+        self.inner.duration(path)
+    }
+}
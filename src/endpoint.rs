@@ -0,0 +1,662 @@
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Smoothing factor of the response time moving average.
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+
+/// How long [`EndpointPool::acquire`] waits for a free slot before looking
+/// again, so health changes and expired cooldowns are noticed.
+const RESELECT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How the next endpoint is chosen for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Strategy {
+    /// Cycle through endpoints in order.
+    #[default]
+    RoundRobin,
+    /// Cycle through endpoints proportionally to their `url=weight` weights.
+    Weighted,
+    /// Prefer the endpoint with the fewest requests in flight.
+    LeastOutstanding,
+    /// Prefer the endpoint with the lowest expected wait, estimated as the
+    /// moving average of its response time times its in-flight requests plus one.
+    Latency,
+}
+
+/// Largest accepted endpoint weight.
+pub const MAX_WEIGHT: u32 = 1000;
+
+/// Splits an endpoint spec of the form `url`, `url=weight` or `url#weight`.
+///
+/// A trailing `=<number>` is read as the weight only when the URL has no
+/// query string, so `http://host/vad?batch=8` keeps its query; such URLs take
+/// a weight as `http://host/vad?batch=8#2`. Weights range from 1 to
+/// [`MAX_WEIGHT`] and default to 1.
+pub fn parse_endpoint(spec: &str) -> Result<(String, u32)> {
+    let (url, weight) = match spec.rsplit_once('#') {
+        Some(split) => split,
+        None => match spec.rsplit_once('=') {
+            Some((url, weight))
+                if !url.contains('?')
+                    && !weight.is_empty()
+                    && weight.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                (url, weight)
+            }
+            _ => return Ok((spec.to_string(), 1)),
+        },
+    };
+    if url.is_empty() {
+        anyhow::bail!("Missing endpoint URL in: {spec}");
+    }
+
+    let weight: u32 = weight
+        .parse()
+        .with_context(|| format!("Invalid endpoint weight in: {spec}"))?;
+    if !(1..=MAX_WEIGHT).contains(&weight) {
+        anyhow::bail!("Endpoint weight must be between 1 and {MAX_WEIGHT}: {spec}");
+    }
+
+    Ok((url.to_string(), weight))
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Smooth weighted round-robin order over endpoints with the given weights.
+///
+/// Weights are divided by their greatest common divisor, so the schedule is
+/// as short as the proportions allow.
+fn weighted_schedule(weights: &[u32]) -> Vec<usize> {
+    let divisor = weights.iter().copied().fold(0, gcd).max(1);
+    let weights: Vec<u32> = weights.iter().map(|&w| w / divisor).collect();
+    let weights = &weights[..];
+    let total: i64 = weights.iter().map(|&w| i64::from(w)).sum();
+    let mut current = vec![0i64; weights.len()];
+    let mut schedule = Vec::with_capacity(total as usize);
+
+    for _ in 0..total {
+        for (c, &w) in current.iter_mut().zip(weights) {
+            *c += i64::from(w);
+        }
+        let best = (0..current.len())
+            .max_by_key(|&i| (current[i], -(i as i64)))
+            .unwrap();
+        current[best] -= total;
+        schedule.push(best);
+    }
+
+    schedule
+}
+
+/// Settings for ejecting endpoints that keep failing.
+///
+/// After `failure_threshold` consecutive failures an endpoint is removed from
+/// the rotation for `cooldown`. Once the cooldown has elapsed a single probe
+/// request is let through: success puts the endpoint back into rotation, a
+/// failure ejects it for another cooldown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    /// Consecutive failures that eject an endpoint.
+    pub failure_threshold: u32,
+    /// How long an ejected endpoint stays out of the rotation.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    probing: bool,
+}
+
+impl EndpointState {
+    /// Whether the breaker lets a request through now: closed, or half-open
+    /// with its probe slot free.
+    fn admits(&self, now: Instant) -> bool {
+        match self.open_until {
+            None => true,
+            Some(until) => until <= now && !self.probing,
+        }
+    }
+
+    /// Whether a request may be sent now. Claims the probe slot of a half-open breaker.
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        if !self.admits(now) {
+            return false;
+        }
+        if self.open_until.is_some() {
+            self.probing = true;
+        }
+        true
+    }
+}
+
+/// A single VAD API endpoint together with its circuit breaker, health and latency state.
+#[derive(Debug)]
+pub struct Endpoint {
+    url: String,
+    weight: u32,
+    state: Mutex<EndpointState>,
+    healthy: AtomicBool,
+    /// Moving average of response times in microseconds; 0 until the first sample.
+    latency_ewma_us: AtomicU64,
+}
+
+impl Endpoint {
+    fn new(url: String, weight: u32) -> Self {
+        Self {
+            url,
+            weight,
+            state: Mutex::new(EndpointState::default()),
+            healthy: AtomicBool::new(true),
+            latency_ewma_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Address requests are sent to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Relative share of requests under [`Strategy::Weighted`].
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Moving average of response times, or `None` before the first response.
+    pub fn latency(&self) -> Option<Duration> {
+        match self.latency_ewma_us.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let sample = (elapsed.as_micros() as u64).max(1);
+        let _ = self
+            .latency_ewma_us
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |prev| {
+                Some(match prev {
+                    0 => sample,
+                    prev => (LATENCY_EWMA_ALPHA * sample as f64
+                        + (1.0 - LATENCY_EWMA_ALPHA) * prev as f64)
+                        .max(1.0) as u64,
+                })
+            });
+    }
+
+    /// Whether the endpoint is currently ejected from the rotation.
+    pub fn is_ejected(&self) -> bool {
+        self.state.lock().unwrap().open_until.is_some()
+    }
+
+    /// Whether the last health probe succeeded. Endpoints start out healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    /// Updates the health flag. Returns `true` if the value changed.
+    pub fn set_healthy(&self, healthy: bool) -> bool {
+        self.healthy.swap(healthy, Ordering::Relaxed) != healthy
+    }
+}
+
+/// A claimed request slot on an endpoint, released when dropped.
+#[derive(Debug)]
+pub struct EndpointLease<'a> {
+    pool: &'a EndpointPool,
+    idx: usize,
+}
+
+impl EndpointLease<'_> {
+    /// Index of the leased endpoint in its pool.
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// Address of the leased endpoint.
+    pub fn url(&self) -> &str {
+        self.pool.endpoints[self.idx].url()
+    }
+}
+
+impl Drop for EndpointLease<'_> {
+    fn drop(&mut self) {
+        self.pool.in_flight.lock().unwrap()[self.idx] -= 1;
+        self.pool.slot_freed.notify_all();
+    }
+}
+
+/// Set of endpoints selected according to a [`Strategy`], skipping ejected and unhealthy ones.
+///
+/// Each endpoint accepts at most `max_in_flight` concurrent requests; callers
+/// of [`EndpointPool::acquire`] block until a slot frees up.
+#[derive(Debug)]
+pub struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+    next: AtomicUsize,
+    strategy: Strategy,
+    schedule: Vec<usize>,
+    breaker: CircuitBreaker,
+    max_in_flight: usize,
+    in_flight: Mutex<Vec<usize>>,
+    slot_freed: Condvar,
+}
+
+impl EndpointPool {
+    /// Creates a round-robin pool over the given endpoint specs (see [`parse_endpoint`])
+    /// with the default circuit breaker and one in-flight request per endpoint.
+    pub fn new(specs: Vec<String>) -> Result<Self> {
+        let endpoints = specs
+            .iter()
+            .map(|spec| parse_endpoint(spec).map(|(url, weight)| Endpoint::new(url, weight)))
+            .collect::<Result<Vec<_>>>()?;
+        let weights: Vec<u32> = endpoints.iter().map(Endpoint::weight).collect();
+
+        Ok(Self {
+            schedule: weighted_schedule(&weights),
+            in_flight: Mutex::new(vec![0; endpoints.len()]),
+            endpoints,
+            next: AtomicUsize::new(0),
+            strategy: Strategy::default(),
+            breaker: CircuitBreaker::default(),
+            max_in_flight: 1,
+            slot_freed: Condvar::new(),
+        })
+    }
+
+    /// Sets the load balancing strategy.
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Load balancing strategy in use.
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Sets the circuit breaker settings.
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Sets how many requests each endpoint may have in flight at once.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Maximum concurrent requests per endpoint.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Total concurrent requests the pool accepts across all endpoints.
+    pub fn capacity(&self) -> usize {
+        self.endpoints.len() * self.max_in_flight
+    }
+
+    /// Requests currently in flight on the endpoint at index `idx`.
+    pub fn in_flight(&self, idx: usize) -> usize {
+        self.in_flight.lock().unwrap()[idx]
+    }
+
+    /// Number of endpoints in the pool.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether the pool has no endpoints.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Endpoint at index `idx`.
+    pub fn get(&self, idx: usize) -> &Endpoint {
+        &self.endpoints[idx]
+    }
+
+    /// Iterates over all endpoints.
+    pub fn iter(&self) -> impl Iterator<Item = &Endpoint> {
+        self.endpoints.iter()
+    }
+
+    /// Claims a request slot on the next endpoint, skipping ejected or unhealthy
+    /// endpoints and those in `exclude`.
+    ///
+    /// Blocks while every available (healthy, not ejected) candidate is at its
+    /// in-flight limit. Only when no candidate is available at all is the one
+    /// whose cooldown ends first used, so that work keeps flowing. Returns
+    /// `None` only when all endpoints are excluded.
+    pub fn acquire(&self, exclude: &[usize]) -> Option<EndpointLease<'_>> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        loop {
+            match self.select(exclude, &in_flight) {
+                Selection::Ready(idx) => {
+                    in_flight[idx] += 1;
+                    return Some(EndpointLease { pool: self, idx });
+                }
+                Selection::Full => {
+                    in_flight = self
+                        .slot_freed
+                        .wait_timeout(in_flight, RESELECT_INTERVAL)
+                        .unwrap()
+                        .0;
+                }
+                Selection::Excluded => return None,
+            }
+        }
+    }
+
+    /// Endpoint indices in order of preference for the next request.
+    fn preference_order(&self, in_flight: &[usize]) -> Vec<usize> {
+        let len = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut order: Vec<usize> = (0..len).map(|i| (start + i) % len).collect();
+
+        match self.strategy {
+            Strategy::RoundRobin => {}
+            Strategy::Weighted => {
+                order.clear();
+                let schedule = &self.schedule;
+                for k in 0..schedule.len() {
+                    let idx = schedule[(start + k) % schedule.len()];
+                    if !order.contains(&idx) {
+                        order.push(idx);
+                        if order.len() == len {
+                            break;
+                        }
+                    }
+                }
+            }
+            Strategy::LeastOutstanding => order.sort_by_key(|&i| in_flight[i]),
+            Strategy::Latency => {
+                let expected_wait = |i: usize| {
+                    let latency = self.endpoints[i].latency().unwrap_or_default();
+                    latency.as_secs_f64() * (in_flight[i] + 1) as f64
+                };
+                order.sort_by(|&a, &b| expected_wait(a).total_cmp(&expected_wait(b)));
+            }
+        }
+
+        order
+    }
+
+    fn select(&self, exclude: &[usize], in_flight: &[usize]) -> Selection {
+        let order = self.preference_order(in_flight);
+        let now = Instant::now();
+
+        let candidates = || order.iter().copied().filter(|i| !exclude.contains(i));
+        if candidates().next().is_none() {
+            return Selection::Excluded;
+        }
+
+        let has_slot = |i: usize| in_flight[i] < self.max_in_flight;
+        let available: Vec<usize> = candidates()
+            .filter(|&i| {
+                let endpoint = &self.endpoints[i];
+                endpoint.is_healthy() && endpoint.state.lock().unwrap().admits(now)
+            })
+            .collect();
+        if !available.is_empty() {
+            // Wait for an available endpoint rather than spill onto a broken one.
+            return available
+                .into_iter()
+                .find(|&i| has_slot(i) && self.endpoints[i].state.lock().unwrap().try_acquire(now))
+                .map_or(Selection::Full, Selection::Ready);
+        }
+
+        // No candidate is healthy with a closed breaker.
+        match candidates()
+            .filter(|&i| has_slot(i))
+            .min_by_key(|&i| self.endpoints[i].state.lock().unwrap().open_until)
+        {
+            Some(idx) => Selection::Ready(idx),
+            None => Selection::Full,
+        }
+    }
+
+    /// Records the response time of a call, feeding [`Strategy::Latency`].
+    pub fn record_latency(&self, idx: usize, elapsed: Duration) {
+        self.endpoints[idx].record_latency(elapsed);
+    }
+
+    /// Records a successful call, closing the endpoint's breaker.
+    pub fn record_success(&self, idx: usize) {
+        let mut state = self.endpoints[idx].state.lock().unwrap();
+        if state.open_until.is_some() {
+            info!(endpoint = %self.endpoints[idx].url, "Endpoint recovered");
+        }
+        *state = EndpointState::default();
+    }
+
+    /// Records a failed call, ejecting the endpoint once the threshold is reached.
+    pub fn record_failure(&self, idx: usize) {
+        let mut state = self.endpoints[idx].state.lock().unwrap();
+        state.consecutive_failures += 1;
+        state.probing = false;
+
+        if state.consecutive_failures >= self.breaker.failure_threshold {
+            state.open_until = Some(Instant::now() + self.breaker.cooldown);
+            warn!(
+                endpoint = %self.endpoints[idx].url,
+                cooldown = ?self.breaker.cooldown,
+                failures = state.consecutive_failures,
+                "Endpoint ejected after consecutive failures"
+            );
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Selection {
+    Ready(usize),
+    Full,
+    Excluded,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(specs: &[&str]) -> EndpointPool {
+        EndpointPool::new(specs.iter().map(|s| s.to_string()).collect()).unwrap()
+    }
+
+    fn eject(pool: &EndpointPool, idx: usize) {
+        for _ in 0..pool.breaker.failure_threshold {
+            pool.record_failure(idx);
+        }
+        assert!(pool.get(idx).is_ejected());
+    }
+
+    #[test]
+    fn parse_endpoint_reads_weights_after_hash() {
+        assert_eq!(
+            parse_endpoint("http://h:8000/vad").unwrap(),
+            ("http://h:8000/vad".to_string(), 1)
+        );
+        assert_eq!(
+            parse_endpoint("http://h:8000/vad#4").unwrap(),
+            ("http://h:8000/vad".to_string(), 4)
+        );
+        assert_eq!(
+            parse_endpoint("http://h/vad?batch=8#2").unwrap(),
+            ("http://h/vad?batch=8".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn parse_endpoint_reads_weights_after_equals_sign() {
+        assert_eq!(
+            parse_endpoint("http://h:8000/vad=2").unwrap(),
+            ("http://h:8000/vad".to_string(), 2)
+        );
+        assert_eq!(
+            parse_endpoint("http://h/vad=x").unwrap(),
+            ("http://h/vad=x".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn parse_endpoint_keeps_query_strings() {
+        assert_eq!(
+            parse_endpoint("http://h/vad?batch=8").unwrap(),
+            ("http://h/vad?batch=8".to_string(), 1)
+        );
+        assert_eq!(
+            parse_endpoint("http://h/vad?model=x").unwrap(),
+            ("http://h/vad?model=x".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn parse_endpoint_rejects_invalid_and_ambiguous_specs() {
+        for spec in [
+            "http://h/vad#0",
+            "http://h/vad#",
+            "http://h/vad#x",
+            "http://h/vad#1001",
+            "http://h/vad=4000000000",
+            "#3",
+            "=3",
+        ] {
+            assert!(parse_endpoint(spec).is_err(), "{spec} was accepted");
+        }
+    }
+
+    #[test]
+    fn weighted_schedule_follows_weights() {
+        let schedule = weighted_schedule(&[3, 1, 2]);
+        assert_eq!(schedule.len(), 6);
+        for (idx, weight) in [(0, 3), (1, 1), (2, 2)] {
+            assert_eq!(schedule.iter().filter(|&&i| i == idx).count(), weight);
+        }
+        // Smooth: the heaviest endpoint is never picked three times in a row.
+        assert!(schedule.windows(3).all(|w| w != [0, 0, 0]));
+    }
+
+    #[test]
+    fn weighted_schedule_is_plain_cycle_for_equal_weights() {
+        assert_eq!(weighted_schedule(&[1, 1, 1]), vec![0, 1, 2]);
+        assert_eq!(weighted_schedule(&[1000, 1000]), vec![0, 1]);
+    }
+
+    #[test]
+    fn weighted_schedule_reduces_weights() {
+        assert_eq!(weighted_schedule(&[400, 200]), weighted_schedule(&[2, 1]));
+        assert_eq!(weighted_schedule(&[1000, 999]).len(), 1999);
+    }
+
+    #[test]
+    fn weighted_pool_spreads_requests_by_weight() {
+        let pool = pool(&["http://a#3", "http://b#1"]).with_strategy(Strategy::Weighted);
+        let mut counts = [0; 2];
+        for _ in 0..40 {
+            match pool.select(&[], &[0, 0]) {
+                Selection::Ready(idx) => counts[idx] += 1,
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(counts, [30, 10]);
+    }
+
+    #[test]
+    fn select_skips_unhealthy_endpoints() {
+        let pool = pool(&["http://a", "http://b"]);
+        pool.get(1).set_healthy(false);
+        for _ in 0..4 {
+            assert_eq!(pool.select(&[], &[0, 0]), Selection::Ready(0));
+        }
+    }
+
+    #[test]
+    fn select_waits_for_full_healthy_endpoint_instead_of_unhealthy_one() {
+        let pool = pool(&["http://a", "http://b"]);
+        pool.get(1).set_healthy(false);
+        assert_eq!(pool.select(&[], &[1, 0]), Selection::Full);
+    }
+
+    #[test]
+    fn select_waits_for_full_healthy_endpoint_instead_of_ejected_one() {
+        let pool = pool(&["http://a", "http://b"]);
+        eject(&pool, 1);
+        assert_eq!(pool.select(&[], &[0, 0]), Selection::Ready(0));
+        assert_eq!(pool.select(&[], &[1, 0]), Selection::Full);
+    }
+
+    #[test]
+    fn select_falls_back_to_earliest_cooldown_when_nothing_is_available() {
+        let pool = pool(&["http://a", "http://b"]);
+        eject(&pool, 1);
+        eject(&pool, 0);
+        for _ in 0..4 {
+            assert_eq!(pool.select(&[], &[0, 0]), Selection::Ready(1));
+        }
+        assert_eq!(pool.select(&[], &[0, 1]), Selection::Ready(0));
+        assert_eq!(pool.select(&[], &[1, 1]), Selection::Full);
+    }
+
+    #[test]
+    fn select_lets_one_probe_through_after_cooldown() {
+        let pool = pool(&["http://a", "http://b"]).with_breaker(CircuitBreaker {
+            failure_threshold: 1,
+            cooldown: Duration::ZERO,
+        });
+        eject(&pool, 1);
+        assert_eq!(pool.select(&[0], &[0, 0]), Selection::Ready(1));
+        // The probe is in flight, so the healthy endpoint is waited for.
+        assert_eq!(pool.select(&[], &[1, 0]), Selection::Full);
+        pool.record_success(1);
+        assert!(!pool.get(1).is_ejected());
+    }
+
+    #[test]
+    fn select_reports_exclusion_of_all_endpoints() {
+        let pool = pool(&["http://a", "http://b"]);
+        assert_eq!(pool.select(&[0, 1], &[0, 0]), Selection::Excluded);
+        assert!(pool.acquire(&[0, 1]).is_none());
+    }
+
+    #[test]
+    fn acquire_counts_slots_and_releases_them_on_drop() {
+        let pool = pool(&["http://a", "http://b"]).with_max_in_flight(2);
+        let leases: Vec<_> = (0..4).map(|_| pool.acquire(&[]).unwrap()).collect();
+        assert_eq!((pool.in_flight(0), pool.in_flight(1)), (2, 2));
+        drop(leases);
+        assert_eq!((pool.in_flight(0), pool.in_flight(1)), (0, 0));
+    }
+
+    #[test]
+    fn acquire_wakes_up_when_a_slot_frees() {
+        let pool = pool(&["http://a", "http://b"]);
+        pool.get(1).set_healthy(false);
+        let lease = pool.acquire(&[]).unwrap();
+        std::thread::scope(|s| {
+            let waiter = s.spawn(|| pool.acquire(&[]).unwrap().idx());
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!waiter.is_finished());
+            drop(lease);
+            assert_eq!(waiter.join().unwrap(), 0);
+        });
+    }
+}
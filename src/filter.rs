@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the optional ignore file read from the root of the input directory.
+pub const VADIGNORE_FILE: &str = ".vadignore";
+
+/// Gitignore-style include and exclude patterns, relative to the input directory.
+///
+/// A file is selected when it matches an include pattern (or none are given)
+/// and neither it nor any parent directory matches an exclude pattern.
+/// Patterns follow `.gitignore` syntax: `scratch/` matches directories only,
+/// `/takes` is anchored at the input directory, `**` spans directories and
+/// `!pattern` re-includes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Also exclude what the input directory's [`VADIGNORE_FILE`] lists.
+    pub vadignore: bool,
+}
+
+impl PathFilter {
+    /// Whether the filter lets every file through.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && !self.vadignore
+    }
+
+    /// Compiles the patterns for paths below `root`.
+    pub fn build(&self, root: &Path) -> Result<PathMatcher> {
+        let include = if self.include.is_empty() {
+            None
+        } else {
+            let builder = builder(root, &self.include, "--include")?;
+            Some(
+                builder
+                    .build()
+                    .context("Failed to compile include patterns")?,
+            )
+        };
+
+        let mut builder = builder(root, &self.exclude, "--exclude")?;
+        let ignore_file = root.join(VADIGNORE_FILE);
+        if self.vadignore
+            && ignore_file.is_file()
+            && let Some(e) = builder.add(&ignore_file)
+        {
+            return Err(e)
+                .with_context(|| format!("Invalid pattern in: {}", ignore_file.display()));
+        }
+        let exclude = builder
+            .build()
+            .context("Failed to compile exclude patterns")?;
+
+        Ok(PathMatcher { include, exclude })
+    }
+}
+
+/// Compiled form of a [`PathFilter`], bound to the input directory.
+#[derive(Debug, Clone)]
+pub struct PathMatcher {
+    include: Option<Gitignore>,
+    exclude: Gitignore,
+}
+
+impl PathMatcher {
+    /// Whether `path` (below the root) or one of its parents is excluded.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.exclude
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+
+    /// Whether the file at `path` (below the root) is selected.
+    pub fn is_selected(&self, path: &Path) -> bool {
+        let included = self
+            .include
+            .as_ref()
+            .is_none_or(|include| include.matched_path_or_any_parents(path, false).is_ignore());
+        included && !self.is_excluded(path, false)
+    }
+}
+
+/// Starts a matcher for `root` with the patterns given by `flag`.
+fn builder(root: &Path, patterns: &[String], flag: &str) -> Result<GitignoreBuilder> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .with_context(|| format!("Invalid {flag} pattern: {pattern}"))?;
+    }
+    Ok(builder)
+}
+
+/// How the input directory is traversed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalkOptions {
+    /// Follow symbolic links to files and directories; loops are detected and reported.
+    pub follow_symlinks: bool,
+    /// Deepest level descended into; files directly in the input directory are at depth 1.
+    pub max_depth: Option<usize>,
+    /// Skip files and directories whose name starts with a dot.
+    pub skip_hidden: bool,
+}
+
+/// A part of the input tree that could not be walked.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct WalkError {
+    /// Path that could not be read, if known.
+    pub path: Option<PathBuf>,
+    pub error: String,
+}
+
+impl WalkError {
+    pub(crate) fn from_walkdir(error: &walkdir::Error) -> Self {
+        let message = match error.loop_ancestor() {
+            Some(ancestor) => format!("symlink loop back to {}", ancestor.display()),
+            None => error
+                .io_error()
+                .map_or_else(|| error.to_string(), ToString::to_string),
+        };
+        Self {
+            path: error.path().map(Path::to_path_buf),
+            error: message,
+        }
+    }
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.display(), self.error),
+            None => f.write_str(&self.error),
+        }
+    }
+}
+
+/// Whether the file name of `path` starts with a dot.
+pub(crate) fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use std::time::Duration;
+use ureq::http::Uri;
+
+/// Readiness probing of VAD servers.
+///
+/// Every endpoint is probed with `GET <scheme>://<host>[:port]<path>`; any
+/// 2xx response marks it healthy. Unhealthy endpoints are excluded from the
+/// rotation until a later probe succeeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    /// Path of the health endpoint on each server, e.g. `/health`.
+    pub path: String,
+    /// Time between periodic probes during a run.
+    pub interval: Duration,
+    /// Timeout for a single probe request.
+    pub timeout: Duration,
+    /// Number of healthy endpoints required before the run starts (0 disables waiting).
+    pub min_ready: usize,
+    /// How long to wait for `min_ready` endpoints before giving up.
+    pub ready_timeout: Duration,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            path: "/health".to_string(),
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(5),
+            min_ready: 1,
+            ready_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Health URL for an endpoint: the endpoint's scheme and authority joined with `path`.
+    pub fn url_for(&self, endpoint: &str) -> Result<String> {
+        let uri: Uri = endpoint
+            .parse()
+            .with_context(|| format!("Invalid API address: {endpoint}"))?;
+        let scheme = uri.scheme_str().unwrap_or("http");
+        let authority = uri
+            .authority()
+            .with_context(|| format!("API address has no host: {endpoint}"))?;
+        let path = self.path.trim_start_matches('/');
+
+        Ok(format!("{scheme}://{authority}/{path}"))
+    }
+
+    /// Sends one probe to `endpoint`. Returns `true` when it answered with a 2xx status.
+    pub fn probe(&self, agent: &ureq::Agent, endpoint: &str) -> bool {
+        let Ok(url) = self.url_for(endpoint) else {
+            return false;
+        };
+
+        agent
+            .get(&url)
+            .config()
+            .timeout_global(Some(self.timeout))
+            .build()
+            .call()
+            .is_ok_and(|resp| resp.status().is_success())
+    }
+}
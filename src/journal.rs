@@ -0,0 +1,295 @@
+use crate::validate::Rejection;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default journal file name, created in the output directory.
+pub const JOURNAL_FILE: &str = ".vad-journal.jsonl";
+
+/// Decides which input files are skipped as already processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SkipPolicy {
+    /// Skip files whose `<output mirror>/<file stem>` already exists.
+    #[default]
+    ExistingOutput,
+    /// Skip files the journal records as done and that are unchanged since.
+    ///
+    /// Files recorded as invalid are inspected again, since a change of the
+    /// conversion or validation settings may now accept them.
+    Resume,
+    /// Process every file.
+    Force,
+}
+
+/// Final state of a file in the journal.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalStatus {
+    Done,
+    Invalid,
+    Failed,
+}
+
+/// Size and modification time used to detect changed inputs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub size: u64,
+    pub mtime_ns: u64,
+}
+
+impl Fingerprint {
+    /// Reads the fingerprint of the file at `path`.
+    pub fn of(path: &Path) -> Result<Self> {
+        let meta =
+            fs::metadata(path).with_context(|| format!("Failed to stat: {}", path.display()))?;
+        let mtime_ns = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos() as u64);
+
+        Ok(Self {
+            size: meta.len(),
+            mtime_ns,
+        })
+    }
+}
+
+/// One line of the journal.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// Input path relative to the input directory.
+    pub input: PathBuf,
+    pub status: JournalStatus,
+    #[serde(flatten)]
+    pub fingerprint: Fingerprint,
+    /// Endpoint that handled the last attempt, if any.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Wall time spent on the file, in milliseconds.
+    pub duration_ms: u64,
+    /// Speech segments the backend reported for a done file, if it returned them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segments: Option<usize>,
+    /// Seconds of detected speech, alongside `segments`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speech_secs: Option<f64>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Why an invalid file was rejected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<Rejection>,
+    /// Unix time in seconds when the entry was written.
+    pub finished_at: u64,
+}
+
+/// Append-only JSONL record of per-file outcomes, used by [`SkipPolicy::Resume`].
+///
+/// Later lines override earlier ones for the same input, so the file can be
+/// appended to across runs without rewriting it.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    entries: HashMap<PathBuf, JournalEntry>,
+    /// `None` for journals opened with [`Journal::read_only`].
+    file: Option<Mutex<File>>,
+}
+
+impl Journal {
+    /// Opens (or creates) the journal at `path` and loads its entries.
+    ///
+    /// Unparseable lines, e.g. a partially written last line after a crash, are ignored.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let mut journal = Self::read_only(path)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal.path)
+            .with_context(|| format!("Failed to open journal: {}", journal.path.display()))?;
+        journal.file = Some(Mutex::new(file));
+
+        Ok(journal)
+    }
+
+    /// Loads the entries of the journal at `path`, if it exists, without
+    /// creating it; [`Journal::record`] does nothing on the result.
+    pub fn read_only(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut entries = HashMap::new();
+
+        if path.exists() {
+            let file = File::open(&path)
+                .with_context(|| format!("Failed to open journal: {}", path.display()))?;
+            for line in BufReader::new(file).lines() {
+                let line =
+                    line.with_context(|| format!("Failed to read journal: {}", path.display()))?;
+                if let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) {
+                    entries.insert(entry.input.clone(), entry);
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            entries,
+            file: None,
+        })
+    }
+
+    /// Location of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entry loaded for `input` (relative to the input directory) when the journal was opened.
+    pub fn get(&self, input: &Path) -> Option<&JournalEntry> {
+        self.entries.get(input)
+    }
+
+    /// Whether `input` finished as done in an earlier run and is unchanged since.
+    pub fn is_settled(&self, input: &Path, fingerprint: Fingerprint) -> bool {
+        self.get(input).is_some_and(|entry| {
+            entry.status == JournalStatus::Done && entry.fingerprint == fingerprint
+        })
+    }
+
+    /// Appends an entry and flushes it to disk.
+    pub fn record(&self, entry: &JournalEntry) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let mut file = file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .with_context(|| format!("Failed to write journal: {}", self.path.display()))
+    }
+}
+
+/// Current Unix time in seconds.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const FP: Fingerprint = Fingerprint {
+        size: 100,
+        mtime_ns: 1,
+    };
+
+    fn entry(input: &str, status: JournalStatus, fingerprint: Fingerprint) -> JournalEntry {
+        JournalEntry {
+            input: PathBuf::from(input),
+            status,
+            fingerprint,
+            endpoint: None,
+            duration_ms: 0,
+            segments: None,
+            speech_secs: None,
+            error: None,
+            rejections: Vec::new(),
+            finished_at: 0,
+        }
+    }
+
+    #[test]
+    fn records_are_read_back() {
+        let dir = TempDir::new();
+        let path = dir.path().join(JOURNAL_FILE);
+        let journal = Journal::open(&path).unwrap();
+        journal
+            .record(&entry("a.wav", JournalStatus::Done, FP))
+            .unwrap();
+
+        let journal = Journal::read_only(&path).unwrap();
+        assert_eq!(
+            journal.get(Path::new("a.wav")),
+            Some(&entry("a.wav", JournalStatus::Done, FP))
+        );
+        assert!(journal.get(Path::new("b.wav")).is_none());
+    }
+
+    #[test]
+    fn later_lines_override_earlier_ones() {
+        let dir = TempDir::new();
+        let path = dir.path().join(JOURNAL_FILE);
+        let journal = Journal::open(&path).unwrap();
+        journal
+            .record(&entry("a.wav", JournalStatus::Failed, FP))
+            .unwrap();
+        journal
+            .record(&entry("a.wav", JournalStatus::Done, FP))
+            .unwrap();
+        journal
+            .record(&entry("b.wav", JournalStatus::Done, FP))
+            .unwrap();
+        journal
+            .record(&entry("b.wav", JournalStatus::Failed, FP))
+            .unwrap();
+
+        let journal = Journal::open(&path).unwrap();
+        assert!(journal.is_settled(Path::new("a.wav"), FP));
+        assert!(!journal.is_settled(Path::new("b.wav"), FP));
+    }
+
+    #[test]
+    fn only_unchanged_done_files_are_settled() {
+        let dir = TempDir::new();
+        let path = dir.path().join(JOURNAL_FILE);
+        let journal = Journal::open(&path).unwrap();
+        journal
+            .record(&entry("done.wav", JournalStatus::Done, FP))
+            .unwrap();
+        journal
+            .record(&entry("invalid.wav", JournalStatus::Invalid, FP))
+            .unwrap();
+
+        let journal = Journal::read_only(&path).unwrap();
+        assert!(journal.is_settled(Path::new("done.wav"), FP));
+        let touched = Fingerprint { mtime_ns: 2, ..FP };
+        let grown = Fingerprint { size: 101, ..FP };
+        assert!(!journal.is_settled(Path::new("done.wav"), touched));
+        assert!(!journal.is_settled(Path::new("done.wav"), grown));
+        assert!(!journal.is_settled(Path::new("invalid.wav"), FP));
+        assert!(!journal.is_settled(Path::new("missing.wav"), FP));
+    }
+
+    #[test]
+    fn torn_last_line_is_ignored() {
+        let dir = TempDir::new();
+        let mut line = serde_json::to_string(&entry("a.wav", JournalStatus::Done, FP)).unwrap();
+        let torn = serde_json::to_string(&entry("b.wav", JournalStatus::Done, FP)).unwrap();
+        line.push('\n');
+        line.push_str(&torn[..torn.len() / 2]);
+        let path = dir.write(JOURNAL_FILE, line);
+
+        let journal = Journal::open(&path).unwrap();
+        assert!(journal.is_settled(Path::new("a.wav"), FP));
+        assert!(journal.get(Path::new("b.wav")).is_none());
+    }
+
+    #[test]
+    fn read_only_journal_is_not_created_or_written() {
+        let dir = TempDir::new();
+        let path = dir.path().join(JOURNAL_FILE);
+        let journal = Journal::read_only(&path).unwrap();
+        journal
+            .record(&entry("a.wav", JournalStatus::Done, FP))
+            .unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,64 @@
+//! Batch Voice Activity Detection over trees of WAV files using external VAD APIs.
+//!
+//! The crate exposes the same pipeline used by the `wav-files-vad-api` binary:
+//! a [`VadClient`] that talks to one or more VAD servers (or the in-process
+//! [`LocalVad`] engine), a [`BatchJob`] builder that walks an input directory
+//! and dispatches files, and a [`BatchReport`] summarising the run.
+//!
+//! ```no_run
+//! use wav_files_vad_api::{BatchJob, VadClient};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let client = VadClient::new(["http://127.0.0.1:8001/vad"])?;
+//! let report = BatchJob::new("raw_audio", "processed_audio", client).run()?;
+//! println!("{} processed, {} failed", report.processed, report.failed());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod backend;
+pub mod batch;
+pub mod client;
+pub mod convert;
+pub mod decode;
+pub mod endpoint;
+pub mod filter;
+pub mod health;
+pub mod journal;
+pub mod local;
+pub mod manifest;
+pub mod metrics;
+pub mod output;
+pub mod plan;
+pub mod progress;
+pub mod report;
+pub mod response;
+pub mod retry;
+pub mod timeout;
+pub mod upload;
+pub mod validate;
+pub mod wav;
+
+#[cfg(test)]
+mod test_util;
+
+pub use backend::{Backend, EndpointStats, RequestError};
+pub use batch::{BatchJob, BatchReport};
+pub use client::VadClient;
+pub use convert::ConvertOptions;
+pub use decode::{DecodedAudio, Decoder, DecoderRegistry};
+pub use endpoint::{CircuitBreaker, Strategy};
+pub use filter::{PathFilter, WalkError, WalkOptions};
+pub use health::HealthCheck;
+pub use journal::{Journal, SkipPolicy};
+pub use local::{LocalVad, LocalVadConfig};
+pub use metrics::Metrics;
+pub use output::OutputFormat;
+pub use plan::{Plan, PlanAction, PlanSummary, PlannedFile};
+pub use progress::{Progress, ProgressMode, StderrWriter};
+pub use report::{FileStatus, ReportRecord, ReportSummary, RunReport};
+pub use response::{Segment, VadOutput, VadResponse};
+pub use retry::RetryPolicy;
+pub use timeout::TimeoutPolicy;
+pub use upload::RequestMode;
+pub use validate::{Rejection, ValidationLimits, ValidationMode};
@@ -0,0 +1,313 @@
+use crate::backend::Backend;
+use crate::response::{Segment, VadOutput, VadResponse};
+use crate::upload;
+use anyhow::{Context, Result};
+use hound::{WavReader, WavWriter};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// Endpoint name reported for files processed by [`LocalVad`].
+pub const LOCAL_ENDPOINT: &str = "local";
+
+/// Tuning of the built-in energy / zero-crossing VAD.
+///
+/// A frame is voiced speech when its RMS level reaches `energy_threshold_db`,
+/// and unvoiced speech (fricatives) when it is within `unvoiced_margin_db`
+/// below that level and its zero-crossing rate reaches `zcr_threshold`.
+/// Speech is extended by `hangover_ms` after the last speech frame, and
+/// segments shorter than `min_speech_ms` are dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVadConfig {
+    /// Analysis frame length in milliseconds.
+    pub frame_ms: u32,
+    /// RMS level in dBFS above which a frame counts as voiced speech.
+    pub energy_threshold_db: f32,
+    /// How far below `energy_threshold_db` unvoiced speech may be, in dB.
+    pub unvoiced_margin_db: f32,
+    /// Zero crossings per sample above which a quieter frame counts as unvoiced speech.
+    pub zcr_threshold: f32,
+    /// Time speech is held after the last speech frame, in milliseconds.
+    pub hangover_ms: u32,
+    /// Minimum segment length kept, in milliseconds.
+    pub min_speech_ms: u32,
+}
+
+impl Default for LocalVadConfig {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            energy_threshold_db: -40.0,
+            unvoiced_margin_db: 10.0,
+            zcr_threshold: 0.25,
+            hangover_ms: 240,
+            min_speech_ms: 120,
+        }
+    }
+}
+
+/// VAD engine that runs in-process on the samples of validated WAV files.
+///
+/// Speech segments are written as `segment_NNN.wav` files to
+/// `<output mirror>/<file stem>/`, together with their timestamps in
+/// `segments.json`.
+#[derive(Debug, Clone, Default)]
+pub struct LocalVad {
+    config: LocalVadConfig,
+    threads: Option<usize>,
+}
+
+impl LocalVad {
+    /// Creates an engine with the given tuning.
+    pub fn new(config: LocalVadConfig) -> Self {
+        Self {
+            config,
+            threads: None,
+        }
+    }
+
+    /// Sets how many files are processed in parallel. Defaults to the number of CPUs.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Tuning in use.
+    pub fn config(&self) -> &LocalVadConfig {
+        &self.config
+    }
+
+    /// Detects speech in 16-bit mono `samples` recorded at `sample_rate`.
+    ///
+    /// Returns segments as sample ranges `[start, end)`.
+    pub fn detect(&self, samples: &[i16], sample_rate: u32) -> Vec<(usize, usize)> {
+        let cfg = &self.config;
+        let frame_len = (sample_rate as usize * cfg.frame_ms as usize / 1000).max(1);
+        let hangover_frames = cfg.hangover_ms.div_ceil(cfg.frame_ms.max(1)) as usize;
+        let min_len = sample_rate as usize * cfg.min_speech_ms as usize / 1000;
+
+        let mut segments = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        let mut silent_frames = 0;
+
+        for (i, frame) in samples.chunks(frame_len).enumerate() {
+            let start = i * frame_len;
+            let end = start + frame.len();
+
+            if self.is_speech(frame) {
+                silent_frames = 0;
+                current = Some(current.map_or((start, end), |(s, _)| (s, end)));
+            } else if let Some((s, e)) = current {
+                silent_frames += 1;
+                if silent_frames <= hangover_frames {
+                    current = Some((s, end));
+                } else {
+                    segments.push((s, e));
+                    current = None;
+                }
+            }
+        }
+        segments.extend(current);
+
+        segments.retain(|&(s, e)| e - s >= min_len);
+        segments
+    }
+
+    fn is_speech(&self, frame: &[i16]) -> bool {
+        let cfg = &self.config;
+        let energy_db = frame_energy_db(frame);
+
+        energy_db >= cfg.energy_threshold_db
+            || (energy_db >= cfg.energy_threshold_db - cfg.unvoiced_margin_db
+                && zero_crossing_rate(frame) >= cfg.zcr_threshold)
+    }
+}
+
+impl Backend for LocalVad {
+    fn process(&self, input_file: &Path, _audio: Duration, output_dir: &Path) -> Result<VadOutput> {
+        let mut reader = WavReader::open(input_file)
+            .with_context(|| format!("Failed to open WAV file: {}", input_file.display()))?;
+        let spec = reader.spec();
+        let samples = reader
+            .samples::<i16>()
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to read samples: {}", input_file.display()))?;
+
+        let dir = output_dir.join(input_file.file_stem().unwrap_or_default());
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
+
+        let rate = f64::from(spec.sample_rate);
+        let mut response = VadResponse::default();
+        for (n, (start, end)) in self
+            .detect(&samples, spec.sample_rate)
+            .into_iter()
+            .enumerate()
+        {
+            let path = dir.join(format!("segment_{n:03}.wav"));
+            let mut writer = WavWriter::create(&path, spec)
+                .with_context(|| format!("Failed to create: {}", path.display()))?;
+            for &sample in &samples[start..end] {
+                writer.write_sample(sample)?;
+            }
+            writer
+                .finalize()
+                .with_context(|| format!("Failed to write: {}", path.display()))?;
+
+            response.segments.push(Segment {
+                start: start as f64 / rate,
+                end: end as f64 / rate,
+                probability: None,
+            });
+            response
+                .output_files
+                .push(path.to_string_lossy().to_string());
+        }
+
+        upload::write_segments(&dir, &response)?;
+
+        Ok(VadOutput {
+            endpoint: LOCAL_ENDPOINT.to_string(),
+            status: None,
+            body: Some(response),
+        })
+    }
+
+    fn capacity(&self) -> usize {
+        self.threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+/// RMS level of a frame in dBFS.
+fn frame_energy_db(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let sum_sq: f64 = frame.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+    let rms = (sum_sq / frame.len() as f64).sqrt() / f64::from(i16::MAX);
+
+    (20.0 * rms.max(1e-10).log10()) as f32
+}
+
+/// Fraction of adjacent sample pairs whose sign differs.
+fn zero_crossing_rate(frame: &[i16]) -> f32 {
+    if frame.len() < 2 {
+        return 0.0;
+    }
+    let crossings = frame
+        .windows(2)
+        .filter(|w| (w[0] >= 0) != (w[1] >= 0))
+        .count();
+
+    crossings as f32 / (frame.len() - 1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+    /// Samples in one default 30 ms frame.
+    const FRAME: usize = 480;
+
+    fn tone(frames: usize) -> Vec<i16> {
+        (0..frames * FRAME)
+            .map(|n| {
+                let phase = std::f32::consts::TAU * 440.0 * n as f32 / RATE as f32;
+                (8_000.0 * phase.sin()) as i16
+            })
+            .collect()
+    }
+
+    fn silence(frames: usize) -> Vec<i16> {
+        vec![0; frames * FRAME]
+    }
+
+    fn vad(hangover_ms: u32, min_speech_ms: u32) -> LocalVad {
+        LocalVad::new(LocalVadConfig {
+            hangover_ms,
+            min_speech_ms,
+            ..LocalVadConfig::default()
+        })
+    }
+
+    #[test]
+    fn frame_energy_db_measures_rms_level() {
+        assert!(frame_energy_db(&[i16::MAX, -i16::MAX]).abs() < 0.01);
+        // An 8000 peak sine has an RMS of 8000 / sqrt(2).
+        let expected = 20.0 * (8_000.0 / 2f32.sqrt() / 32_767.0).log10();
+        assert!((frame_energy_db(&tone(1)) - expected).abs() < 0.1);
+        assert!(frame_energy_db(&silence(1)) < -150.0);
+        assert_eq!(frame_energy_db(&[]), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn zero_crossing_rate_counts_sign_changes() {
+        assert_eq!(zero_crossing_rate(&[100, -100, 100, -100, 100]), 1.0);
+        assert_eq!(zero_crossing_rate(&[100, 100, -100, -100, 100]), 0.5);
+        assert_eq!(zero_crossing_rate(&[5; 10]), 0.0);
+        assert_eq!(zero_crossing_rate(&[5]), 0.0);
+    }
+
+    #[test]
+    fn detect_finds_tone_between_silences() {
+        let samples = [silence(10), tone(30), silence(30)].concat();
+        // The segment is held for the 8 frames of the 240 ms hangover.
+        assert_eq!(
+            vad(240, 120).detect(&samples, RATE),
+            [(10 * FRAME, (40 + 8) * FRAME)]
+        );
+        assert_eq!(
+            vad(0, 120).detect(&samples, RATE),
+            [(10 * FRAME, 40 * FRAME)]
+        );
+    }
+
+    #[test]
+    fn detect_ignores_silence() {
+        assert!(vad(240, 120).detect(&silence(50), RATE).is_empty());
+        assert!(vad(240, 120).detect(&[], RATE).is_empty());
+    }
+
+    #[test]
+    fn hangover_merges_short_gaps() {
+        let short_gap = [tone(10), silence(5), tone(10)].concat();
+        assert_eq!(vad(240, 120).detect(&short_gap, RATE), [(0, 25 * FRAME)]);
+
+        let long_gap = [tone(10), silence(20), tone(10)].concat();
+        assert_eq!(
+            vad(240, 120).detect(&long_gap, RATE),
+            [(0, 18 * FRAME), (30 * FRAME, 40 * FRAME)]
+        );
+    }
+
+    #[test]
+    fn segments_shorter_than_min_speech_are_dropped() {
+        // 60 ms and 150 ms bursts, with a 120 ms minimum.
+        let samples = [tone(2), silence(20), tone(5), silence(20)].concat();
+        assert_eq!(
+            vad(0, 120).detect(&samples, RATE),
+            [(22 * FRAME, 27 * FRAME)]
+        );
+    }
+
+    #[test]
+    fn quiet_noise_counts_as_unvoiced_speech() {
+        // About -44 dBFS: below the -40 dB voiced threshold, within the 10 dB margin.
+        let hiss: Vec<i16> = (0..10 * FRAME)
+            .map(|n| if n % 2 == 0 { 200 } else { -200 })
+            .collect();
+        assert_eq!(vad(0, 0).detect(&hiss, RATE), [(0, 10 * FRAME)]);
+
+        // Same level without zero crossings, e.g. a DC offset.
+        let hum = vec![200; 10 * FRAME];
+        assert!(vad(0, 0).detect(&hum, RATE).is_empty());
+    }
+
+    #[test]
+    fn capacity_follows_threads() {
+        assert_eq!(LocalVad::default().with_threads(3).capacity(), 3);
+    }
+}
@@ -1,17 +1,55 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use hound::WavReader;
-use rayon::{ThreadPoolBuilder, prelude::*};
-use serde::Serialize;
-use std::fs::create_dir_all;
-use std::path::{Path, PathBuf};
+use anyhow::Context;
+use anyhow::Result;
+use clap::builder::RangedU64ValueParser;
+use clap::{ArgAction, Parser, ValueEnum};
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use walkdir::WalkDir;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use wav_files_vad_api::manifest::{read_file_list, read_manifest};
+use wav_files_vad_api::report::csv_summary_path;
+use wav_files_vad_api::retry::DEFAULT_RETRYABLE_STATUSES;
+use wav_files_vad_api::{
+    Backend, BatchJob, BatchReport, CircuitBreaker, ConvertOptions, DecoderRegistry, HealthCheck,
+    LocalVad, LocalVadConfig, OutputFormat, PathFilter, ProgressMode, RequestMode, RetryPolicy,
+    SkipPolicy, StderrWriter, Strategy, TimeoutPolicy, VadClient, ValidationLimits, ValidationMode,
+    WalkOptions,
+};
+
+/// Exit code when some inputs were rejected as invalid but nothing failed.
+const EXIT_INVALID: u8 = 3;
+/// Exit code when some files failed with an HTTP or I/O error, or input paths were unreadable.
+const EXIT_FAILED: u8 = 4;
+/// Exit code when the run stopped early at `--max-failures`.
+const EXIT_ABORTED: u8 = 5;
+
+/// Where VAD runs.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BackendKind {
+    /// Send files to the external VAD API servers given by --addr-api
+    Api,
+    /// Run the built-in energy/zero-crossing VAD in-process
+    Local,
+}
+
+/// How log events are written.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line, with the file and request spans of each event
+    Json,
+}
 
 /// CLI arguments for wav-files-vad-api
 #[derive(Parser, Debug)]
-#[command(author, version, about = "Recursively extract speech from WAV files using an external VAD API", long_about = None)]
+#[command(author, version, about = "Recursively extract speech from WAV (and FLAC/MP3/Ogg) files using an external VAD API or a built-in engine", long_about = None)]
 struct Args {
     /// Input directory containing WAV files (processed recursively)
     input_dir: PathBuf,
@@ -19,143 +57,549 @@ struct Args {
     /// Output directory for speech files
     output_dir: PathBuf,
 
-    /// Comma-separated list of API server addresses
+    /// Where VAD runs
+    #[arg(long, value_enum, default_value_t = BackendKind::Api)]
+    backend: BackendKind,
+
+    /// Comma-separated list of API server addresses, optionally weighted as `url=weight` (or `url#weight` for URLs with a query string)
     #[arg(long, value_delimiter = ',')]
     addr_api: Vec<String>,
 
+    /// How requests are balanced across API servers
+    #[arg(long, value_enum, default_value_t = Strategy::RoundRobin)]
+    balance: Strategy,
+
     /// Model to use for VAD
     #[arg(long)]
     model: Option<String>,
-}
 
-#[derive(Serialize)]
-struct VadRequestBody {
-    input_file: String,
-    output_dir: String,
-    model: Option<String>,
-}
+    /// Downmix, resample and requantize files that are not 16 kHz mono 16-bit PCM (8/16/24/32-bit int or 32-bit float) instead of skipping them; 16 kHz mono files in other bit depths are requantized regardless
+    #[arg(long)]
+    convert: bool,
+
+    /// Add TPDF dither when requantizing converted audio to 16 bits
+    #[arg(long, requires = "convert")]
+    dither: bool,
+
+    /// Comma-separated extensions of compressed inputs to decode, besides WAV (flac, mp3, ogg, oga, opus)
+    #[arg(long, value_delimiter = ',')]
+    input_formats: Vec<String>,
+
+    /// How thoroughly files are checked before VAD
+    #[arg(long, value_enum, default_value_t = ValidationMode::Header)]
+    validate: ValidationMode,
+
+    /// Deep validation: reject files shorter than this many milliseconds
+    #[arg(long, default_value_t = 0)]
+    min_duration_ms: u64,
+
+    /// Deep validation: reject files longer than this many milliseconds
+    #[arg(long)]
+    max_duration_ms: Option<u64>,
+
+    /// Deep validation: reject files whose peak level stays below this dBFS value
+    #[arg(long, default_value_t = -60.0, allow_negative_numbers = true)]
+    silence_threshold_db: f32,
+
+    /// Deep validation: reject files with a larger fraction of samples at full scale (0.0 - 1.0)
+    #[arg(long, default_value_t = 0.001)]
+    max_clipped_fraction: f64,
+
+    /// Deep validation: reject files whose mean sample value exceeds this fraction of full scale
+    #[arg(long, default_value_t = 0.05)]
+    max_dc_offset: f64,
+
+    /// Process only the files listed in this text file (one path per line, relative to INPUT_DIR)
+    #[arg(long)]
+    file_list: Option<PathBuf>,
+
+    /// Process only the files in this CSV (with a header row) or JSONL manifest
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Manifest column or key holding the file path [default: path, audio_filepath, file or filename]
+    #[arg(long, requires = "manifest")]
+    manifest_column: Option<String>,
+
+    /// Only process files matching this gitignore-style pattern (repeatable, relative to INPUT_DIR)
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this gitignore-style pattern (repeatable, relative to INPUT_DIR)
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Do not read exclude patterns from INPUT_DIR/.vadignore
+    #[arg(long)]
+    no_vadignore: bool,
+
+    /// Follow symbolic links to files and directories (symlink loops are reported and skipped)
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Maximum directory depth to descend into; 1 only processes files directly in INPUT_DIR
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    max_depth: Option<usize>,
+
+    /// Skip files and directories whose name starts with a dot
+    #[arg(long)]
+    skip_hidden: bool,
+
+    /// Log more: -v for debug events such as every processed file, -vv for trace
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less: -q for warnings and errors only, -qq for errors only
+    #[arg(short, long, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Format of log events (RUST_LOG, when set, overrides -v/-q)
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Append log events to this file instead of stderr
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// How progress is shown on stderr
+    #[arg(long, value_enum, default_value_t = ProgressMode::Auto)]
+    progress: ProgressMode,
+
+    /// Seconds between progress lines when progress is logged rather than drawn as a bar
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    progress_interval_secs: u64,
+
+    /// Walk and validate the inputs and print what a run would do, without contacting any API server
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write the dry-run plan to this file, as CSV for a `.csv` extension and JSON otherwise
+    #[arg(long, requires = "dry_run")]
+    plan: Option<PathBuf>,
+
+    /// Format of the --plan file, overriding the extension
+    #[arg(long, value_enum, requires = "plan")]
+    plan_format: Option<OutputFormat>,
+
+    /// Write a report of every file's outcome, endpoint, HTTP status, latency and audio duration, plus totals,
+    /// to this file, as CSV for a `.csv` extension and JSON otherwise
+    #[arg(long, conflicts_with = "dry_run")]
+    report: Option<PathBuf>,
+
+    /// Format of the --report file, overriding the extension
+    #[arg(long, value_enum, requires = "report")]
+    report_format: Option<OutputFormat>,
+
+    /// Serve Prometheus metrics on http://<ADDR>/metrics during the run, e.g. 0.0.0.0:9184
+    #[arg(long)]
+    metrics_addr: Option<SocketAddr>,
+
+    /// Write Prometheus metrics to this file for the node-exporter textfile collector (use a `.prom` extension)
+    #[arg(long)]
+    metrics_textfile: Option<PathBuf>,
+
+    /// Seconds between rewrites of the --metrics-textfile file
+    #[arg(long, default_value_t = 15, requires = "metrics_textfile", value_parser = clap::value_parser!(u64).range(1..))]
+    metrics_interval_secs: u64,
+
+    /// Stop dispatching files after this many have failed with an HTTP or I/O error
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "fail_fast")]
+    max_failures: Option<u64>,
+
+    /// Stop dispatching files after the first failure (same as --max-failures 1)
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Skip only files the journal records as done and unchanged; rerun failed or changed files
+    #[arg(long, conflicts_with = "force")]
+    resume: bool,
+
+    /// Reprocess every file, ignoring existing outputs and the journal
+    #[arg(long)]
+    force: bool,
+
+    /// Journal file recording per-file outcomes [default: <OUTPUT_DIR>/.vad-journal.jsonl]
+    #[arg(long)]
+    journal: Option<PathBuf>,
+
+    /// How audio reaches the API servers: shared filesystem paths or uploaded bytes
+    #[arg(long, value_enum, default_value_t = RequestMode::SharedPath)]
+    request_mode: RequestMode,
 
-/// Validates a WAV file matches the expected format: mono, 16-bit PCM, 16kHz sample rate.
-fn validate_wav(path: &Path) -> Result<bool> {
-    let reader = WavReader::open(path)
-        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    /// Maximum concurrent requests sent to each API server
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    concurrency_per_endpoint: usize,
 
-    let spec = reader.spec();
-    Ok(spec.channels == 1 && spec.sample_rate == 16000 && spec.bits_per_sample == 16)
+    /// Global cap on concurrent requests across all API servers
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    max_concurrency: Option<usize>,
+
+    /// Seconds allowed to connect to an API server (0 disables the limit)
+    #[arg(long, default_value_t = 10)]
+    connect_timeout: u64,
+
+    /// Seconds allowed for each request attempt, from connecting to reading the response
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    request_timeout: Option<u64>,
+
+    /// Extra request seconds per minute of audio, added to --request-timeout
+    #[arg(long, requires = "request_timeout", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout_per_audio_minute: Option<u64>,
+
+    /// Maximum attempts per file, including the first one (1 disables retries)
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Delay before the first retry, in milliseconds (doubled on every retry)
+    #[arg(long, default_value_t = 500)]
+    retry_base_delay_ms: u64,
+
+    /// Upper bound for a single retry delay, in milliseconds
+    #[arg(long, default_value_t = 30_000)]
+    retry_max_delay_ms: u64,
+
+    /// Fraction of each retry delay that is randomised (0.0 - 1.0)
+    #[arg(long, default_value_t = 0.5)]
+    retry_jitter: f64,
+
+    /// Comma-separated HTTP status codes that are retried
+    #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_RETRYABLE_STATUSES.to_vec())]
+    retry_status: Vec<u16>,
+
+    /// Do not retry connection, timeout and other I/O errors
+    #[arg(long)]
+    no_retry_io: bool,
+
+    /// Consecutive connection errors or 5xx responses that eject an endpoint
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    breaker_threshold: u32,
+
+    /// Seconds an ejected endpoint stays out of the rotation before it is re-probed
+    #[arg(long, default_value_t = 30)]
+    breaker_cooldown_secs: u64,
+
+    /// Path of the health endpoint probed on every API server (e.g. /health); enables health checks
+    #[arg(long)]
+    health_path: Option<String>,
+
+    /// Seconds between health probes during the run
+    #[arg(long, default_value_t = 10)]
+    health_interval_secs: u64,
+
+    /// Timeout for a single health probe, in seconds
+    #[arg(long, default_value_t = 5)]
+    health_timeout_secs: u64,
+
+    /// Number of healthy servers to wait for before starting (0 starts immediately)
+    #[arg(long, default_value_t = 1)]
+    wait_ready: usize,
+
+    /// Maximum seconds to wait for --wait-ready servers
+    #[arg(long, default_value_t = 300)]
+    wait_ready_timeout_secs: u64,
+
+    /// Local backend: analysis frame length in milliseconds
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    local_frame_ms: u32,
+
+    /// Local backend: frame level in dBFS above which a frame is voiced speech
+    #[arg(long, default_value_t = -40.0, allow_negative_numbers = true)]
+    local_energy_threshold_db: f32,
+
+    /// Local backend: dB below the energy threshold where high zero-crossing frames still count as speech
+    #[arg(long, default_value_t = 10.0)]
+    local_unvoiced_margin_db: f32,
+
+    /// Local backend: zero crossings per sample above which a quieter frame is unvoiced speech
+    #[arg(long, default_value_t = 0.25)]
+    local_zcr_threshold: f32,
+
+    /// Local backend: milliseconds speech is held after the last speech frame
+    #[arg(long, default_value_t = 240)]
+    local_hangover_ms: u32,
+
+    /// Local backend: minimum speech segment length in milliseconds
+    #[arg(long, default_value_t = 120)]
+    local_min_speech_ms: u32,
+
+    /// Local backend: files processed in parallel [default: number of CPUs]
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    local_threads: Option<usize>,
 }
 
-fn main() -> Result<()> {
-    let mut args = Args::parse();
-
-    // Resolve to absolute paths to avoid ambiguity
-    args.input_dir = args.input_dir.canonicalize().with_context(|| {
-        format!(
-            "Failed to find canonical path for input directory: {}",
-            args.input_dir.display()
-        )
-    })?;
-
-    // Ensure output directory exists
-    create_dir_all(&args.output_dir).with_context(|| {
-        format!(
-            "Failed to create output directory: {}",
-            args.output_dir.display()
-        )
-    })?;
-    args.output_dir = args.output_dir.canonicalize().with_context(|| {
-        format!(
-            "Failed to find canonical path for output directory: {}",
-            args.output_dir.display()
-        )
-    })?;
-
-    let processed = AtomicUsize::new(0);
-    let skipped = AtomicUsize::new(0);
+/// Builds the built-in engine from the local backend arguments.
+fn local_vad(args: &Args) -> LocalVad {
+    let local = LocalVad::new(LocalVadConfig {
+        frame_ms: args.local_frame_ms,
+        energy_threshold_db: args.local_energy_threshold_db,
+        unvoiced_margin_db: args.local_unvoiced_margin_db,
+        zcr_threshold: args.local_zcr_threshold,
+        hangover_ms: args.local_hangover_ms,
+        min_speech_ms: args.local_min_speech_ms,
+    });
+    match args.local_threads {
+        Some(threads) => local.with_threads(threads),
+        None => local,
+    }
+}
 
+/// Builds the HTTP client from the API-related arguments.
+fn api_client(args: &Args) -> Result<VadClient> {
     if args.addr_api.is_empty() {
         anyhow::bail!("At least one API address must be provided via --addr-api");
     }
 
-    let api_endpoints = Mutex::new(args.addr_api.iter().cycle());
-
-    let wav_files: Vec<_> = WalkDir::new(&args.input_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("wav"))
-        .collect();
-
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(args.addr_api.len())
-        .build()
-        .context("Failed to create thread pool")?;
-
-    pool.install(|| {
-        wav_files.par_iter().for_each(|entry| {
-            let input_path = entry.path();
-
-            // The closure for `for_each` doesn't return a Result, so we handle errors inside.
-            let process = || -> Result<()> {
-                if !validate_wav(input_path)? {
-                    eprintln!("Skipping invalid WAV file: {}", input_path.display());
-                    skipped.fetch_add(1, Ordering::SeqCst);
-                    return Ok(());
-                }
-
-                let relative = input_path.strip_prefix(&args.input_dir)?;
-                let output_path = args.output_dir.join(relative);
-
-                let input_name = input_path.file_stem().unwrap();
-                let output_file_path = output_path.join(input_name);
-                if output_file_path.exists() {
-                    skipped.fetch_add(1, Ordering::SeqCst);
-                    return Ok(());
-                }
-
-                if let Some(parent) = output_path.parent() {
-                    create_dir_all(parent).with_context(|| {
-                        format!(
-                            "Failed to create output directory for: {}",
-                            output_path.display()
-                        )
-                    })?;
-                }
-
-                let body = VadRequestBody {
-                    input_file: input_path.to_string_lossy().to_string(),
-                    output_dir: output_path.to_string_lossy().to_string(),
-                    model: args.model.clone(),
-                };
-
-                let api_addr = api_endpoints.lock().unwrap().next().unwrap();
-
-                let resp = ureq::post(api_addr).send_json(&body)?;
-
-                if resp.status() == 200 {
-                    processed.fetch_add(1, Ordering::SeqCst);
-                } else {
-                    eprintln!(
-                        "VAD failed for {}: API returned status {}",
-                        input_path.display(),
-                        resp.status()
-                    );
-                    skipped.fetch_add(1, Ordering::SeqCst);
-                }
-                Ok(())
-            };
-
-            if let Err(e) = process() {
-                eprintln!("Error processing {}: {:?}", input_path.display(), e);
-                skipped.fetch_add(1, Ordering::SeqCst);
-            }
+    let retry = RetryPolicy {
+        max_attempts: args.max_attempts,
+        base_delay: Duration::from_millis(args.retry_base_delay_ms),
+        max_delay: Duration::from_millis(args.retry_max_delay_ms),
+        jitter: args.retry_jitter,
+        retryable_statuses: args.retry_status.clone(),
+        retry_io_errors: !args.no_retry_io,
+    };
+
+    let mut client = VadClient::new(args.addr_api.clone())?
+        .with_model(args.model.clone())
+        .with_request_mode(args.request_mode)
+        .with_retry(retry)
+        .with_timeouts(TimeoutPolicy {
+            connect: (args.connect_timeout > 0).then(|| Duration::from_secs(args.connect_timeout)),
+            request: args.request_timeout.map(Duration::from_secs),
+            per_audio_minute: args.timeout_per_audio_minute.map(Duration::from_secs),
+        })
+        .with_strategy(args.balance)
+        .with_concurrency_per_endpoint(args.concurrency_per_endpoint)
+        .with_circuit_breaker(CircuitBreaker {
+            failure_threshold: args.breaker_threshold,
+            cooldown: Duration::from_secs(args.breaker_cooldown_secs),
+        });
+
+    if let Some(path) = &args.health_path {
+        client = client.with_health_check(HealthCheck {
+            path: path.clone(),
+            interval: Duration::from_secs(args.health_interval_secs),
+            timeout: Duration::from_secs(args.health_timeout_secs),
+            min_ready: args.wait_ready,
+            ready_timeout: Duration::from_secs(args.wait_ready_timeout_secs),
         });
+    }
+
+    Ok(client)
+}
+
+/// Installs the global log subscriber configured by `-v`/`-q`, `--log-format` and `--log-file`.
+fn init_logging(args: &Args) -> Result<()> {
+    let level = match i16::from(args.verbose) - i16::from(args.quiet) {
+        ..=-2 => LevelFilter::ERROR,
+        -1 => LevelFilter::WARN,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    // Dependencies only log warnings unless asked for through RUST_LOG.
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(format!(
+            "{},wav_files_vad_api={level}",
+            level.min(LevelFilter::WARN)
+        ))
     });
 
+    let (writer, ansi) = match &args.log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+            (BoxMakeWriter::new(Mutex::new(file)), false)
+        }
+        None => (
+            BoxMakeWriter::new(StderrWriter),
+            std::io::stderr().is_terminal(),
+        ),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_target(false);
+    match args.log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_span_list(true).init(),
+    }
+    Ok(())
+}
+
+/// Exit code for a finished run, from the most to the least severe outcome.
+fn exit_code(report: &BatchReport) -> ExitCode {
+    if report.aborted {
+        ExitCode::from(EXIT_ABORTED)
+    } else if report.failed() > 0 || !report.walk_errors.is_empty() {
+        ExitCode::from(EXIT_FAILED)
+    } else if report.invalid > 0 {
+        ExitCode::from(EXIT_INVALID)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+    init_logging(&args)?;
+
+    // A dry run never calls the backend, so it can plan without API servers;
+    // the local engine stands in and is left untouched.
+    let backend: Box<dyn Backend> = match args.backend {
+        BackendKind::Api if !(args.dry_run && args.addr_api.is_empty()) => {
+            Box::new(api_client(&args)?)
+        }
+        BackendKind::Api | BackendKind::Local => Box::new(local_vad(&args)),
+    };
+
+    let skip_policy = if args.force {
+        SkipPolicy::Force
+    } else if args.resume {
+        SkipPolicy::Resume
+    } else {
+        SkipPolicy::ExistingOutput
+    };
+
+    let decoders = DecoderRegistry::with_defaults();
+    if let Some(unknown) = args.input_formats.iter().find(|f| {
+        !f.eq_ignore_ascii_case("wav") && !decoders.extensions().any(|e| f.eq_ignore_ascii_case(e))
+    }) {
+        anyhow::bail!(
+            "Unsupported input format '{unknown}'; expected one of: {}",
+            decoders.extensions().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let mut job = BatchJob::new(args.input_dir, args.output_dir, backend)
+        .decoders(decoders.restrict_to(&args.input_formats))
+        .skip_policy(skip_policy)
+        .filter(PathFilter {
+            include: args.include.clone(),
+            exclude: args.exclude.clone(),
+            vadignore: !args.no_vadignore,
+        })
+        .walk_options(WalkOptions {
+            follow_symlinks: args.follow_symlinks,
+            max_depth: args.max_depth,
+            skip_hidden: args.skip_hidden,
+        })
+        .progress(args.progress)
+        .progress_interval(Duration::from_secs(args.progress_interval_secs))
+        .validation(args.validate)
+        .validation_limits(ValidationLimits {
+            min_duration: Duration::from_millis(args.min_duration_ms),
+            max_duration: args.max_duration_ms.map(Duration::from_millis),
+            silence_threshold_db: args.silence_threshold_db,
+            max_clipped_fraction: args.max_clipped_fraction,
+            max_dc_offset: args.max_dc_offset,
+        })
+        .convert(args.convert)
+        .convert_options(ConvertOptions {
+            dither: args.dither,
+        });
+    if let Some(journal) = args.journal {
+        job = job.journal_path(journal);
+    }
+    if let Some(max) = args.max_concurrency {
+        job = job.max_concurrency(max);
+    }
+    if let Some(addr) = args.metrics_addr {
+        job = job.metrics_addr(addr);
+    }
+    if let Some(path) = &args.metrics_textfile {
+        job = job.metrics_textfile(path, Duration::from_secs(args.metrics_interval_secs));
+    }
+    if args.fail_fast {
+        job = job.max_failures(1);
+    } else if let Some(max) = args.max_failures {
+        job = job.max_failures(max as usize);
+    }
+    if let Some(path) = &args.report {
+        let format = args
+            .report_format
+            .unwrap_or_else(|| OutputFormat::from_path(path));
+        job = job.report(path, format);
+    }
+    if args.file_list.is_some() || args.manifest.is_some() {
+        let mut files = Vec::new();
+        if let Some(path) = &args.file_list {
+            files.extend(read_file_list(path)?);
+        }
+        if let Some(path) = &args.manifest {
+            files.extend(read_manifest(path, args.manifest_column.as_deref())?);
+        }
+        job = job.files(files);
+    }
+
+    if args.dry_run {
+        let plan = job.plan()?;
+        let summary = plan.summary();
+        println!(
+            "Dry run: {} files to process ({} as is, {} converted), {:.2} hours of audio; {} already done, {} invalid, {} unreadable.",
+            summary.process + summary.convert,
+            summary.process,
+            summary.convert,
+            summary.audio_hours,
+            summary.already_done,
+            summary.invalid,
+            summary.unreadable
+        );
+        if let Some(path) = args.plan {
+            let format = args
+                .plan_format
+                .unwrap_or_else(|| OutputFormat::from_path(&path));
+            plan.write(&path, format)?;
+            println!("Plan written to {}", path.display());
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let report = job.run()?;
+
     println!(
-        "VAD complete: {} files processed, {} skipped.",
-        processed.load(Ordering::SeqCst),
-        skipped.load(Ordering::SeqCst)
+        "VAD {}: {} files processed, {} already done, {} invalid, {} failed ({} HTTP, {} I/O errors).",
+        if report.aborted {
+            "aborted"
+        } else {
+            "complete"
+        },
+        report.processed,
+        report.already_done,
+        report.invalid,
+        report.failed(),
+        report.http_errors,
+        report.io_errors
     );
+    if !report.walk_errors.is_empty() {
+        println!(
+            "Warning: {} unreadable input path(s) were skipped.",
+            report.walk_errors.len()
+        );
+    }
+    if report.segment_count > 0 {
+        println!(
+            "Speech detected: {} segments, {:.1}s total.",
+            report.segment_count, report.speech_seconds
+        );
+    }
+    if let Some(path) = args.report {
+        let format = args
+            .report_format
+            .unwrap_or_else(|| OutputFormat::from_path(&path));
+        match format {
+            OutputFormat::Json => println!("Report written to {}", path.display()),
+            OutputFormat::Csv => println!(
+                "Report written to {} (summary in {})",
+                path.display(),
+                csv_summary_path(&path).display()
+            ),
+        }
+    }
 
-    Ok(())
+    Ok(exit_code(&report))
 }
@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Columns (CSV) or keys (JSONL) tried, in order, when no path column is given.
+pub const DEFAULT_PATH_COLUMNS: &[&str] = &["path", "audio_filepath", "file", "filename"];
+
+/// Reads a plain file list: one path per line, blank lines and `#` comments skipped.
+pub fn read_file_list(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for line in lines(path)? {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            paths.push(PathBuf::from(line));
+        }
+    }
+    Ok(paths)
+}
+
+/// Reads the input paths of a CSV (`.csv`) or JSONL (`.jsonl`, `.ndjson`, `.json`) manifest.
+///
+/// CSV manifests need a header row. Paths are taken from `column`, or from the
+/// first of [`DEFAULT_PATH_COLUMNS`] present. JSONL lines may be objects,
+/// looked up the same way, or bare strings.
+pub fn read_manifest(path: &Path, column: Option<&str>) -> Result<Vec<PathBuf>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("csv") => read_csv_manifest(path, column),
+        Some("jsonl" | "ndjson" | "json") => read_jsonl_manifest(path, column),
+        _ => anyhow::bail!(
+            "Unknown manifest format (expected .csv or .jsonl): {}",
+            path.display()
+        ),
+    }
+}
+
+fn read_csv_manifest(path: &Path, column: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut lines = lines(path)?;
+    let header = lines
+        .next()
+        .transpose()?
+        .with_context(|| format!("Empty manifest: {}", path.display()))?;
+    let header = split_csv_line(header.trim_start_matches('\u{feff}'));
+
+    let index = path_columns(column)
+        .iter()
+        .find_map(|name| header.iter().position(|h| h.trim() == *name))
+        .with_context(|| {
+            format!(
+                "No path column ({}) in manifest header: {}",
+                path_columns(column).join(", "),
+                path.display()
+            )
+        })?;
+
+    let mut paths = Vec::new();
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(&line);
+        let value = fields
+            .get(index)
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+            .with_context(|| {
+                format!("Missing path on line {} of: {}", number + 2, path.display())
+            })?;
+        paths.push(PathBuf::from(value));
+    }
+    Ok(paths)
+}
+
+fn read_jsonl_manifest(path: &Path, column: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for (number, line) in lines(path)?.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(&line).with_context(|| {
+            format!("Invalid JSON on line {} of: {}", number + 1, path.display())
+        })?;
+        let entry = match &value {
+            Value::String(s) => Some(s.as_str()),
+            Value::Object(fields) => path_columns(column)
+                .iter()
+                .find_map(|name| fields.get(*name).and_then(Value::as_str)),
+            _ => None,
+        }
+        .with_context(|| format!("Missing path on line {} of: {}", number + 1, path.display()))?;
+        paths.push(PathBuf::from(entry));
+    }
+    Ok(paths)
+}
+
+/// Column names to look for.
+fn path_columns(column: Option<&str>) -> Vec<&str> {
+    column.map_or_else(|| DEFAULT_PATH_COLUMNS.to_vec(), |c| vec![c])
+}
+
+fn lines(path: &Path) -> Result<impl Iterator<Item = Result<String>>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open input list: {}", path.display()))?;
+    let path = path.to_path_buf();
+    Ok(BufReader::new(file).lines().map(move |line| {
+        line.with_context(|| format!("Failed to read input list: {}", path.display()))
+    }))
+}
+
+/// Splits one CSV record, honouring double-quoted fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches(['\r', '\n']).chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn paths(items: &[&str]) -> Vec<PathBuf> {
+        items.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn split_csv_line_handles_quotes() {
+        assert_eq!(split_csv_line("a,b,,c\r\n"), ["a", "b", "", "c"]);
+        assert_eq!(
+            split_csv_line(r#""x, y.wav",2,"say ""hi""""#),
+            ["x, y.wav", "2", r#"say "hi""#]
+        );
+        assert_eq!(split_csv_line(r#""""#), [""]);
+    }
+
+    #[test]
+    fn csv_manifest_uses_default_column_and_quoted_fields() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "default.csv",
+            "id,path,text\n1,a.wav,hello\n\n2,\"dir, with comma/b.wav\",\"say \"\"hi\"\"\"\n",
+        );
+        assert_eq!(
+            read_manifest(&path, None).unwrap(),
+            paths(&["a.wav", "dir, with comma/b.wav"])
+        );
+    }
+
+    #[test]
+    fn csv_manifest_skips_bom_in_header() {
+        let dir = TempDir::new();
+        let path = dir.write("bom.csv", "\u{feff}audio_filepath,duration\nc.wav,1.5\n");
+        assert_eq!(read_manifest(&path, None).unwrap(), paths(&["c.wav"]));
+    }
+
+    #[test]
+    fn csv_manifest_honours_column_option() {
+        let dir = TempDir::new();
+        let path = dir.write("column.csv", "path,clean\nraw/a.wav,clean/a.wav\n");
+        assert_eq!(
+            read_manifest(&path, Some("clean")).unwrap(),
+            paths(&["clean/a.wav"])
+        );
+        assert!(read_manifest(&path, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn csv_manifest_rejects_missing_path() {
+        let dir = TempDir::new();
+        let path = dir.write("missing.csv", "path,text\n,hello\n");
+        assert!(read_manifest(&path, None).is_err());
+    }
+
+    #[test]
+    fn jsonl_manifest_accepts_objects_and_bare_strings() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "mixed.jsonl",
+            "{\"audio_filepath\": \"a.wav\", \"duration\": 1.0}\n\"b.wav\"\n\n{\"file\": \"c.wav\"}\n",
+        );
+        assert_eq!(
+            read_manifest(&path, None).unwrap(),
+            paths(&["a.wav", "b.wav", "c.wav"])
+        );
+    }
+
+    #[test]
+    fn jsonl_manifest_honours_column_option() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "column.jsonl",
+            "{\"path\": \"a.wav\", \"clean\": \"b.wav\"}\n",
+        );
+        assert_eq!(
+            read_manifest(&path, Some("clean")).unwrap(),
+            paths(&["b.wav"])
+        );
+    }
+
+    #[test]
+    fn jsonl_manifest_rejects_invalid_lines() {
+        let dir = TempDir::new();
+        for (name, contents) in [("bad.jsonl", "{not json\n"), ("number.jsonl", "42\n")] {
+            assert!(read_manifest(&dir.write(name, contents), None).is_err());
+        }
+    }
+
+    #[test]
+    fn unknown_manifest_extension_is_rejected() {
+        assert!(read_manifest(Path::new("list.txt"), None).is_err());
+    }
+}
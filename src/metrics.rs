@@ -0,0 +1,413 @@
+use crate::backend::Backend;
+use crate::progress::Progress;
+use crate::report::{FileStatus, ReportRecord};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How often the metrics listener checks for the end of the run between connections.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// Upper bounds of the backend latency buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Upper bounds of the per-file audio duration buckets, in seconds.
+const AUDIO_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0, 1800.0, 3600.0];
+
+const STATUSES: [FileStatus; 5] = [
+    FileStatus::Processed,
+    FileStatus::AlreadyDone,
+    FileStatus::InvalidFormat,
+    FileStatus::HttpError,
+    FileStatus::IoError,
+];
+
+/// A Prometheus histogram with fixed buckets.
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    /// Cumulative count per bucket.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Appends the bucket, sum and count samples; `labels` is empty or ends with a comma.
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            out.push_str(&format!(
+                "{name}_bucket{{{labels}le=\"{bound}\"}} {count}\n"
+            ));
+        }
+        out.push_str(&format!(
+            "{name}_bucket{{{labels}le=\"+Inf\"}} {}\n",
+            self.count
+        ));
+        let labels = labels.trim_end_matches(',');
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        out.push_str(&format!("{name}_sum{labels} {}\n", self.sum));
+        out.push_str(&format!("{name}_count{labels} {}\n", self.count));
+    }
+}
+
+#[derive(Debug)]
+struct MetricsState {
+    /// Finished files, indexed by [`FileStatus`].
+    files: [u64; STATUSES.len()],
+    /// Backend time per file, by endpoint.
+    latency: BTreeMap<String, Histogram>,
+    /// Audio duration of processed files.
+    audio: Histogram,
+}
+
+/// Prometheus metrics of a running batch, exported through an HTTP
+/// `/metrics` listener ([`Metrics::serve`]) or a node-exporter textfile
+/// ([`Metrics::export_every`]).
+#[derive(Debug)]
+pub struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(MetricsState {
+                files: [0; STATUSES.len()],
+                latency: BTreeMap::new(),
+                audio: Histogram::new(AUDIO_BUCKETS),
+            }),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a finished file and its latency and audio duration.
+    pub fn observe(&self, record: &ReportRecord) {
+        let mut state = self.state.lock().unwrap();
+        state.files[record.status as usize] += 1;
+        if let (Some(endpoint), Some(latency_ms)) = (&record.endpoint, record.latency_ms) {
+            state
+                .latency
+                .entry(endpoint.clone())
+                .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+                .observe(latency_ms as f64 / 1000.0);
+        }
+        if record.status == FileStatus::Processed
+            && let Some(audio) = record.audio_secs
+        {
+            state.audio.observe(audio);
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format, along
+    /// with the walk progress and the backend's endpoint load.
+    pub fn render(&self, progress: &Progress, backend: &dyn Backend) -> String {
+        let state = self.state.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP vad_files_total Files that finished, by outcome.\n");
+        out.push_str("# TYPE vad_files_total counter\n");
+        for status in STATUSES {
+            out.push_str(&format!(
+                "vad_files_total{{outcome=\"{}\"}} {}\n",
+                status.as_str(),
+                state.files[status as usize]
+            ));
+        }
+
+        out.push_str("# HELP vad_files_discovered Files found by the input walk so far.\n");
+        out.push_str("# TYPE vad_files_discovered gauge\n");
+        out.push_str(&format!("vad_files_discovered {}\n", progress.found()));
+
+        out.push_str(
+            "# HELP vad_request_duration_seconds Time spent in the backend per file, retries included, by endpoint.\n",
+        );
+        out.push_str("# TYPE vad_request_duration_seconds histogram\n");
+        for (endpoint, histogram) in &state.latency {
+            let labels = format!("endpoint=\"{}\",", escape_label(endpoint));
+            histogram.render(&mut out, "vad_request_duration_seconds", &labels);
+        }
+
+        out.push_str(
+            "# HELP vad_audio_duration_seconds Audio duration of processed files; the sum is the audio processed.\n",
+        );
+        out.push_str("# TYPE vad_audio_duration_seconds histogram\n");
+        state
+            .audio
+            .render(&mut out, "vad_audio_duration_seconds", "");
+
+        let endpoints = backend.endpoint_stats();
+        if !endpoints.is_empty() {
+            out.push_str("# HELP vad_endpoint_in_flight Requests in flight, by endpoint.\n");
+            out.push_str("# TYPE vad_endpoint_in_flight gauge\n");
+            for stats in &endpoints {
+                out.push_str(&format!(
+                    "vad_endpoint_in_flight{{endpoint=\"{}\"}} {}\n",
+                    escape_label(&stats.name),
+                    stats.in_flight
+                ));
+            }
+            out.push_str(
+                "# HELP vad_endpoint_up Whether the endpoint takes requests (healthy and not ejected).\n",
+            );
+            out.push_str("# TYPE vad_endpoint_up gauge\n");
+            for stats in &endpoints {
+                out.push_str(&format!(
+                    "vad_endpoint_up{{endpoint=\"{}\"}} {}\n",
+                    escape_label(&stats.name),
+                    u8::from(stats.available)
+                ));
+            }
+        }
+        out
+    }
+
+    /// Answers `GET /metrics` on `listener` until `stop` is signalled or disconnected.
+    ///
+    /// The listener must be non-blocking, so the end of the run is noticed
+    /// between connections.
+    pub fn serve(
+        &self,
+        listener: &TcpListener,
+        progress: &Progress,
+        backend: &dyn Backend,
+        stop: &Receiver<()>,
+    ) {
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = self.respond(stream, progress, backend) {
+                        debug!(error = %e, "Failed to answer metrics request");
+                    }
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => warn!(error = %e, "Failed to accept metrics connection"),
+            }
+            match stop.recv_timeout(ACCEPT_POLL) {
+                Err(RecvTimeoutError::Timeout) => {}
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    /// Answers one HTTP request on `stream`.
+    fn respond(
+        &self,
+        mut stream: TcpStream,
+        progress: &Progress,
+        backend: &dyn Backend,
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Drain the headers so closing the connection does not reset it.
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (method, target) = (parts.next(), parts.next().unwrap_or_default());
+        let path = target.split('?').next().unwrap_or_default();
+        let (status, body) = if method == Some("GET") && path == "/metrics" {
+            ("200 OK", self.render(progress, backend))
+        } else {
+            (
+                "404 Not Found",
+                "Not found; metrics are at /metrics\n".to_string(),
+            )
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()
+    }
+
+    /// Atomically replaces `path` with the current metrics, for the
+    /// node-exporter textfile collector.
+    pub fn write_textfile(
+        &self,
+        path: &Path,
+        progress: &Progress,
+        backend: &dyn Backend,
+    ) -> io::Result<()> {
+        // The collector only reads `*.prom` files, so it never sees a partial write.
+        let mut temp = OsString::from(path.as_os_str());
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        fs::write(&temp, self.render(progress, backend))?;
+        fs::rename(&temp, path)
+    }
+
+    /// Rewrites the textfile at `path` every `interval`, and a last time when
+    /// `stop` is signalled or disconnected.
+    pub fn export_every(
+        &self,
+        path: &Path,
+        interval: Duration,
+        progress: &Progress,
+        backend: &dyn Backend,
+        stop: &Receiver<()>,
+    ) {
+        loop {
+            if let Err(e) = self.write_textfile(path, progress, backend) {
+                warn!(error = %e, path = %path.display(), "Failed to write metrics textfile");
+            }
+            match stop.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        if let Err(e) = self.write_textfile(path, progress, backend) {
+            warn!(error = %e, path = %path.display(), "Failed to write metrics textfile");
+        }
+    }
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::EndpointStats;
+    use crate::response::VadOutput;
+    use anyhow::Result;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::new(&[1.0, 5.0]);
+        for value in [0.5, 1.0, 3.0, 7.5] {
+            histogram.observe(value);
+        }
+        assert_eq!(histogram.counts, [2, 3]);
+
+        let mut out = String::new();
+        histogram.render(&mut out, "x", "a=\"b\",");
+        assert_eq!(
+            out,
+            "x_bucket{a=\"b\",le=\"1\"} 2\n\
+             x_bucket{a=\"b\",le=\"5\"} 3\n\
+             x_bucket{a=\"b\",le=\"+Inf\"} 4\n\
+             x_sum{a=\"b\"} 12\n\
+             x_count{a=\"b\"} 4\n"
+        );
+
+        let mut out = String::new();
+        Histogram::new(&[1.0]).render(&mut out, "x", "");
+        assert_eq!(
+            out,
+            "x_bucket{le=\"1\"} 0\nx_bucket{le=\"+Inf\"} 0\nx_sum 0\nx_count 0\n"
+        );
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label("plain"), "plain");
+        assert_eq!(escape_label("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+    }
+
+    #[derive(Debug)]
+    struct Endpoints;
+
+    impl Backend for Endpoints {
+        fn process(&self, _: &Path, _: Duration, _: &Path) -> Result<VadOutput> {
+            unreachable!()
+        }
+
+        fn capacity(&self) -> usize {
+            1
+        }
+
+        fn endpoint_stats(&self) -> Vec<EndpointStats> {
+            vec![EndpointStats {
+                name: "http://a/\"vad\"".to_string(),
+                in_flight: 2,
+                latency: None,
+                available: false,
+            }]
+        }
+    }
+
+    #[test]
+    fn render_reports_files_latency_audio_and_endpoints() {
+        let metrics = Metrics::new();
+        let mut record = ReportRecord::new("a.wav", FileStatus::Processed);
+        record.endpoint = Some("http://a/\"vad\"".to_string());
+        record.latency_ms = Some(200);
+        record.audio_secs = Some(4.0);
+        metrics.observe(&record);
+        metrics.observe(&ReportRecord::new("b.wav", FileStatus::InvalidFormat));
+
+        let progress = Progress::new();
+        progress.discovered();
+        progress.discovered();
+        let out = metrics.render(&progress, &Endpoints);
+        for line in [
+            "vad_files_total{outcome=\"processed\"} 1",
+            "vad_files_total{outcome=\"invalid_format\"} 1",
+            "vad_files_total{outcome=\"http_error\"} 0",
+            "vad_files_discovered 2",
+            "vad_request_duration_seconds_bucket{endpoint=\"http://a/\\\"vad\\\"\",le=\"0.1\"} 0",
+            "vad_request_duration_seconds_bucket{endpoint=\"http://a/\\\"vad\\\"\",le=\"0.25\"} 1",
+            "vad_request_duration_seconds_count{endpoint=\"http://a/\\\"vad\\\"\"} 1",
+            "vad_audio_duration_seconds_bucket{le=\"5\"} 1",
+            "vad_audio_duration_seconds_sum 4",
+            "vad_endpoint_in_flight{endpoint=\"http://a/\\\"vad\\\"\"} 2",
+            "vad_endpoint_up{endpoint=\"http://a/\\\"vad\\\"\"} 0",
+        ] {
+            assert!(
+                out.lines().any(|l| l == line),
+                "missing {line:?} in:\n{out}"
+            );
+        }
+    }
+}
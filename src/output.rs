@@ -0,0 +1,29 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+/// File format of machine-readable output such as plans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    /// Format implied by the extension of `path`: `.csv` is CSV, anything else JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => OutputFormat::Csv,
+            _ => OutputFormat::Json,
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+pub(crate) fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
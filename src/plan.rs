@@ -0,0 +1,136 @@
+use crate::output::{OutputFormat, csv_field};
+use crate::validate::Rejection;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// What a real run would do with a file.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    /// Sent to the backend as is.
+    Process,
+    /// Converted or decoded to 16 kHz mono 16-bit PCM, then sent.
+    Convert,
+    /// Skipped as already processed.
+    AlreadyDone,
+    /// Rejected by validation.
+    Invalid,
+    /// The file could not be read; a real run would fail on it.
+    Unreadable,
+}
+
+impl PlanAction {
+    /// Name used in JSON and CSV output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlanAction::Process => "process",
+            PlanAction::Convert => "convert",
+            PlanAction::AlreadyDone => "already_done",
+            PlanAction::Invalid => "invalid",
+            PlanAction::Unreadable => "unreadable",
+        }
+    }
+
+    /// Whether the file would reach the backend.
+    pub fn is_sent(self) -> bool {
+        matches!(self, PlanAction::Process | PlanAction::Convert)
+    }
+}
+
+/// One input file in a [`Plan`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlannedFile {
+    /// Input path relative to the input directory.
+    pub input: PathBuf,
+    pub action: PlanAction,
+    /// Audio duration in seconds, when it was determined.
+    pub duration_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<Rejection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Totals of a [`Plan`].
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PlanSummary {
+    pub process: usize,
+    pub convert: usize,
+    pub already_done: usize,
+    pub invalid: usize,
+    pub unreadable: usize,
+    /// Hours of audio that would be sent to the backend.
+    pub audio_hours: f64,
+}
+
+/// Result of [`BatchJob::plan`](crate::BatchJob::plan): what a run would do, file by file.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Plan {
+    pub files: Vec<PlannedFile>,
+}
+
+impl Plan {
+    /// Counts files per action and sums the audio that would be sent.
+    pub fn summary(&self) -> PlanSummary {
+        let mut summary = PlanSummary::default();
+        for file in &self.files {
+            match file.action {
+                PlanAction::Process => summary.process += 1,
+                PlanAction::Convert => summary.convert += 1,
+                PlanAction::AlreadyDone => summary.already_done += 1,
+                PlanAction::Invalid => summary.invalid += 1,
+                PlanAction::Unreadable => summary.unreadable += 1,
+            }
+            if file.action.is_sent() {
+                summary.audio_hours += file.duration_secs.unwrap_or(0.0) / 3600.0;
+            }
+        }
+        summary
+    }
+
+    /// Writes the plan to `path` as JSON (summary and files) or CSV (one row per file).
+    pub fn write(&self, path: &Path, format: OutputFormat) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create plan: {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+
+        match format {
+            OutputFormat::Json => {
+                #[derive(Serialize)]
+                struct Document<'a> {
+                    summary: PlanSummary,
+                    files: &'a [PlannedFile],
+                }
+                let document = Document {
+                    summary: self.summary(),
+                    files: &self.files,
+                };
+                serde_json::to_writer_pretty(&mut out, &document)?;
+                writeln!(out)?;
+            }
+            OutputFormat::Csv => {
+                writeln!(out, "input,action,duration_secs,reasons,error")?;
+                for file in &self.files {
+                    let reasons: Vec<String> =
+                        file.rejections.iter().map(ToString::to_string).collect();
+                    writeln!(
+                        out,
+                        "{},{},{},{},{}",
+                        csv_field(&file.input.to_string_lossy()),
+                        file.action.as_str(),
+                        file.duration_secs
+                            .map_or(String::new(), |d| format!("{d:.3}")),
+                        csv_field(&reasons.join("; ")),
+                        csv_field(file.error.as_deref().unwrap_or_default()),
+                    )?;
+                }
+            }
+        }
+
+        out.flush()
+            .with_context(|| format!("Failed to write plan: {}", path.display()))
+    }
+}
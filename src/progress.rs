@@ -0,0 +1,292 @@
+use crate::backend::{Backend, EndpointStats};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::info;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// How often the progress bar is redrawn.
+const BAR_REFRESH: Duration = Duration::from_millis(250);
+
+/// The bar [`Progress::draw_bar`] is currently drawing, if any.
+static ACTIVE_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Log writer for stderr that hides the live progress bar while it writes,
+/// so log lines are printed above the bar instead of through it.
+///
+/// Pass it to `tracing_subscriber::fmt().with_writer(...)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StderrWriter;
+
+impl Write for StderrWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Formatted events arrive in a single write, so each is printed whole.
+        let bar = ACTIVE_BAR.lock().unwrap().clone();
+        match bar {
+            Some(bar) => bar.suspend(|| io::stderr().write(buf)),
+            None => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+impl MakeWriter<'_> for StderrWriter {
+    type Writer = StderrWriter;
+
+    fn make_writer(&self) -> Self::Writer {
+        StderrWriter
+    }
+}
+
+/// How progress is shown during a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ProgressMode {
+    /// A live bar when stderr is a terminal, periodic log lines otherwise
+    Auto,
+    /// Always draw the live bar
+    Bar,
+    /// Print a status line at every progress interval
+    Log,
+    /// No progress output
+    #[default]
+    Off,
+}
+
+impl ProgressMode {
+    /// Resolves [`ProgressMode::Auto`] against the current stderr.
+    pub fn resolve(self) -> Self {
+        match self {
+            ProgressMode::Auto if std::io::stderr().is_terminal() => ProgressMode::Bar,
+            ProgressMode::Auto => ProgressMode::Log,
+            mode => mode,
+        }
+    }
+}
+
+/// Shared counters of a running batch.
+///
+/// Discovery runs concurrently with processing, so the total number of files
+/// is only known once the walk has finished.
+#[derive(Debug)]
+pub struct Progress {
+    started: Instant,
+    discovered: AtomicUsize,
+    finished: AtomicUsize,
+    failed: AtomicUsize,
+    audio_us: AtomicU64,
+    walk_done: AtomicBool,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            discovered: AtomicUsize::new(0),
+            finished: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            audio_us: AtomicU64::new(0),
+            walk_done: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a file found by the walk.
+    pub fn discovered(&self) {
+        self.discovered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Marks the walk as complete, fixing the total.
+    pub fn walk_done(&self) {
+        self.walk_done.store(true, Ordering::Release);
+    }
+
+    /// Counts a file whose processing ended, successfully or not, and the
+    /// audio it sent to the backend.
+    pub fn finished(&self, failed: bool, audio: Duration) {
+        self.finished.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+        self.audio_us
+            .fetch_add(audio.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Audio sent to the backend so far.
+    pub fn audio(&self) -> Duration {
+        Duration::from_micros(self.audio_us.load(Ordering::Relaxed))
+    }
+
+    /// Files found so far.
+    pub fn found(&self) -> usize {
+        self.discovered.load(Ordering::Relaxed)
+    }
+
+    /// Total number of files, once the walk has finished.
+    pub fn total(&self) -> Option<usize> {
+        self.walk_done
+            .load(Ordering::Acquire)
+            .then(|| self.discovered.load(Ordering::Relaxed))
+    }
+
+    /// Files whose processing ended.
+    pub fn done(&self) -> usize {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Files that ended in an error.
+    pub fn failures(&self) -> usize {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Time since the run started.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// One-line status, e.g. `120/500 files (24%), 3.1 files/s, 0.0125 audio-h/s,
+    /// 1.7% errors (2), ETA 2m03s`.
+    ///
+    /// While the walk is still running the total is shown as a lower bound
+    /// (`120/400+ files`) and no ETA is given.
+    pub fn status_line(&self) -> String {
+        let done = self.done();
+        let elapsed = self.elapsed().as_secs_f64();
+        let (rate, audio_rate) = if elapsed > 0.0 {
+            (
+                done as f64 / elapsed,
+                self.audio().as_secs_f64() / 3600.0 / elapsed,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let error_rate = if done == 0 {
+            0.0
+        } else {
+            self.failures() as f64 * 100.0 / done as f64
+        };
+
+        let mut line = match self.total() {
+            Some(total) => {
+                let percent = if total == 0 {
+                    100.0
+                } else {
+                    done as f64 * 100.0 / total as f64
+                };
+                format!("{done}/{total} files ({percent:.0}%)")
+            }
+            None => format!("{done}/{}+ files (still scanning)", self.found()),
+        };
+        line.push_str(&format!(
+            ", {rate:.1} files/s, {audio_rate:.4} audio-h/s, {error_rate:.1}% errors ({})",
+            self.failures()
+        ));
+        if let Some(total) = self.total()
+            && rate > 0.0
+        {
+            let remaining = total.saturating_sub(done) as f64 / rate;
+            line.push_str(&format!(
+                ", ETA {}",
+                format_duration(Duration::from_secs_f64(remaining))
+            ));
+        }
+        line
+    }
+
+    /// Logs [`Progress::status_line`] and the backend's endpoint load at info
+    /// level every `interval` until `stop` is signalled or disconnected.
+    pub fn report_every(&self, backend: &dyn Backend, interval: Duration, stop: &Receiver<()>) {
+        loop {
+            match stop.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {
+                    let stats = backend.endpoint_stats();
+                    let mut line = self.status_line();
+                    if !stats.is_empty() {
+                        let endpoints: Vec<String> = stats.iter().map(endpoint_line).collect();
+                        line.push_str(" | ");
+                        line.push_str(&endpoints.join(", "));
+                    }
+                    info!(
+                        done = self.done(),
+                        found = self.found(),
+                        failed = self.failures(),
+                        audio_secs = self.audio().as_secs_f64(),
+                        "Progress: {line}"
+                    );
+                }
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    /// Draws a live progress bar on stderr, with one line per backend endpoint,
+    /// until `stop` is signalled or disconnected. Logs written through
+    /// [`StderrWriter`] meanwhile are printed above the bar.
+    pub fn draw_bar(&self, backend: &dyn Backend, stop: &Receiver<()>) {
+        let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr());
+        bar.set_style(
+            ProgressStyle::with_template("[{elapsed_precise}] {bar:30.cyan/blue} {msg}")
+                .expect("valid progress template")
+                .progress_chars("=> "),
+        );
+        *ACTIVE_BAR.lock().unwrap() = Some(bar.clone());
+
+        loop {
+            bar.set_length(self.total().unwrap_or_else(|| self.found()) as u64);
+            bar.set_position(self.done() as u64);
+
+            let mut message = self.status_line();
+            for stats in backend.endpoint_stats() {
+                message.push_str("\n  ");
+                message.push_str(&endpoint_line(&stats));
+            }
+            bar.set_message(message);
+
+            match stop.recv_timeout(BAR_REFRESH) {
+                Err(RecvTimeoutError::Timeout) => {}
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        ACTIVE_BAR.lock().unwrap().take();
+        bar.finish_and_clear();
+    }
+}
+
+/// Describes one endpoint, e.g. `http://a:8080 3 in flight, 120 ms`.
+fn endpoint_line(stats: &EndpointStats) -> String {
+    let latency = stats
+        .latency
+        .map_or_else(|| "-".to_string(), |l| format!("{} ms", l.as_millis()));
+    let state = if stats.available {
+        ""
+    } else {
+        " (unavailable)"
+    };
+    format!(
+        "{} {} in flight, {latency}{state}",
+        stats.name, stats.in_flight
+    )
+}
+
+/// Formats a duration as `1h02m03s`, `2m03s` or `3s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h{minutes:02}m{seconds:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m{seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
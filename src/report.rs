@@ -0,0 +1,408 @@
+use crate::output::{OutputFormat, csv_field};
+use crate::validate::Rejection;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How a file ended, as listed in a run report.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The backend processed the file.
+    Processed,
+    /// Skipped as already processed.
+    AlreadyDone,
+    /// Rejected by validation.
+    InvalidFormat,
+    /// The backend answered with an error status.
+    HttpError,
+    /// The file, its output or the connection to the backend failed.
+    IoError,
+}
+
+impl FileStatus {
+    /// Name used in JSON and CSV output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileStatus::Processed => "processed",
+            FileStatus::AlreadyDone => "already_done",
+            FileStatus::InvalidFormat => "invalid_format",
+            FileStatus::HttpError => "http_error",
+            FileStatus::IoError => "io_error",
+        }
+    }
+}
+
+/// One input file in a run report.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReportRecord {
+    /// Input path relative to the input directory.
+    pub input: PathBuf,
+    pub status: FileStatus,
+    /// Endpoint that handled the last attempt, if any.
+    pub endpoint: Option<String>,
+    /// HTTP status of the last response, if any.
+    pub http_status: Option<u16>,
+    /// Time spent in the backend, retries included, in milliseconds.
+    pub latency_ms: Option<u64>,
+    /// Audio duration in seconds, when it was determined.
+    pub audio_secs: Option<f64>,
+    /// Speech segments the backend reported, for processed files with a JSON response.
+    pub segments: Option<usize>,
+    /// Seconds of detected speech, alongside `segments`.
+    pub speech_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<Rejection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReportRecord {
+    /// A record for `input` with the given status and nothing else known yet.
+    pub fn new(input: impl Into<PathBuf>, status: FileStatus) -> Self {
+        Self {
+            input: input.into(),
+            status,
+            endpoint: None,
+            http_status: None,
+            latency_ms: None,
+            audio_secs: None,
+            segments: None,
+            speech_secs: None,
+            rejections: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// Totals of a run report.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReportSummary {
+    pub files: usize,
+    pub processed: usize,
+    pub already_done: usize,
+    pub invalid_format: usize,
+    pub http_error: usize,
+    pub io_error: usize,
+    /// Hours of audio the backend processed.
+    pub audio_hours: f64,
+    /// Backend time summed over all files, in milliseconds.
+    pub total_latency_ms: u64,
+    /// Mean backend time of the files that reached it, in milliseconds.
+    pub mean_latency_ms: Option<f64>,
+    /// Wall time of the run, in seconds.
+    pub elapsed_secs: f64,
+}
+
+impl ReportSummary {
+    /// Counts one record.
+    fn add(&mut self, record: &ReportRecord) {
+        self.files += 1;
+        match record.status {
+            FileStatus::Processed => {
+                self.processed += 1;
+                self.audio_hours += record.audio_secs.unwrap_or(0.0) / 3600.0;
+            }
+            FileStatus::AlreadyDone => self.already_done += 1,
+            FileStatus::InvalidFormat => self.invalid_format += 1,
+            FileStatus::HttpError => self.http_error += 1,
+            FileStatus::IoError => self.io_error += 1,
+        }
+        if let Some(latency) = record.latency_ms {
+            self.total_latency_ms += latency;
+        }
+    }
+}
+
+/// Per-file outcomes of a run, written to a JSON or CSV file as files finish.
+///
+/// JSON reports hold `{"files": [...], "summary": {...}}`. CSV reports have
+/// one row per file; the summary goes to a `name,value` sidecar next to them
+/// (see [`RunReport::summary_path`]), so the report stays plain CSV. Records
+/// are streamed, so memory stays flat however many files there are.
+#[derive(Debug)]
+pub struct RunReport {
+    path: PathBuf,
+    format: OutputFormat,
+    state: Mutex<ReportState>,
+}
+
+#[derive(Debug)]
+struct ReportState {
+    out: BufWriter<File>,
+    summary: ReportSummary,
+    /// Files that reached the backend, for the mean latency.
+    timed: usize,
+}
+
+impl RunReport {
+    /// Creates the report at `path`, truncating any existing file.
+    pub fn create(path: impl Into<PathBuf>, format: OutputFormat) -> Result<Self> {
+        let path = path.into();
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create report: {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        match format {
+            OutputFormat::Json => write!(out, "{{\n  \"files\": [")?,
+            OutputFormat::Csv => writeln!(
+                out,
+                "input,status,endpoint,http_status,latency_ms,audio_secs,segments,speech_secs,error"
+            )?,
+        }
+
+        Ok(Self {
+            path,
+            format,
+            state: Mutex::new(ReportState {
+                out,
+                summary: ReportSummary::default(),
+                timed: 0,
+            }),
+        })
+    }
+
+    /// Location of the report file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Location of the summary of a CSV report, e.g. `report.summary.csv`
+    /// for `report.csv`; `None` for JSON reports, which embed it.
+    pub fn summary_path(&self) -> Option<PathBuf> {
+        match self.format {
+            OutputFormat::Json => None,
+            OutputFormat::Csv => Some(csv_summary_path(&self.path)),
+        }
+    }
+
+    /// Appends one file's record.
+    pub fn record(&self, record: &ReportRecord) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let first = state.summary.files == 0;
+        state.summary.add(record);
+        if record.latency_ms.is_some() {
+            state.timed += 1;
+        }
+
+        let out = &mut state.out;
+        match self.format {
+            OutputFormat::Json => {
+                out.write_all(if first { b"\n    " } else { b",\n    " })?;
+                serde_json::to_writer(&mut *out, record)?;
+            }
+            OutputFormat::Csv => {
+                let error = if record.rejections.is_empty() {
+                    record.error.clone().unwrap_or_default()
+                } else {
+                    let reasons: Vec<String> =
+                        record.rejections.iter().map(ToString::to_string).collect();
+                    reasons.join("; ")
+                };
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{}",
+                    csv_field(&record.input.to_string_lossy()),
+                    record.status.as_str(),
+                    csv_field(record.endpoint.as_deref().unwrap_or_default()),
+                    record.http_status.map_or(String::new(), |s| s.to_string()),
+                    record.latency_ms.map_or(String::new(), |l| l.to_string()),
+                    record
+                        .audio_secs
+                        .map_or(String::new(), |d| format!("{d:.3}")),
+                    record.segments.map_or(String::new(), |s| s.to_string()),
+                    record
+                        .speech_secs
+                        .map_or(String::new(), |s| format!("{s:.3}")),
+                    csv_field(&error),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the summary, closes the report and returns the summary.
+    pub fn finish(self, elapsed: Duration) -> Result<ReportSummary> {
+        let summary_path = self.summary_path();
+        let ReportState {
+            mut out,
+            mut summary,
+            timed,
+        } = self.state.into_inner().unwrap();
+        summary.elapsed_secs = elapsed.as_secs_f64();
+        if timed > 0 {
+            summary.mean_latency_ms = Some(summary.total_latency_ms as f64 / timed as f64);
+        }
+
+        match self.format {
+            OutputFormat::Json => {
+                let summary = serde_json::to_string_pretty(&summary)?;
+                write!(
+                    out,
+                    "\n  ],\n  \"summary\": {}\n}}\n",
+                    summary.replace('\n', "\n  ")
+                )?;
+            }
+            OutputFormat::Csv => {
+                if let Some(path) = summary_path {
+                    write_csv_summary(&path, &summary).with_context(|| {
+                        format!("Failed to write report summary: {}", path.display())
+                    })?;
+                }
+            }
+        }
+
+        out.flush()
+            .with_context(|| format!("Failed to write report: {}", self.path.display()))?;
+        Ok(summary)
+    }
+}
+
+/// Summary sidecar of the CSV report at `report`, e.g. `report.summary.csv` for `report.csv`.
+pub fn csv_summary_path(report: &Path) -> PathBuf {
+    report.with_extension("summary.csv")
+}
+
+/// Writes `summary` as `name,value` rows under a header.
+fn write_csv_summary(path: &Path, summary: &ReportSummary) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let optional = |v: Option<f64>| v.map_or(String::new(), |v| format!("{v:.1}"));
+    writeln!(out, "name,value")?;
+    writeln!(out, "files,{}", summary.files)?;
+    writeln!(out, "processed,{}", summary.processed)?;
+    writeln!(out, "already_done,{}", summary.already_done)?;
+    writeln!(out, "invalid_format,{}", summary.invalid_format)?;
+    writeln!(out, "http_error,{}", summary.http_error)?;
+    writeln!(out, "io_error,{}", summary.io_error)?;
+    writeln!(out, "audio_hours,{:.4}", summary.audio_hours)?;
+    writeln!(out, "total_latency_ms,{}", summary.total_latency_ms)?;
+    writeln!(out, "mean_latency_ms,{}", optional(summary.mean_latency_ms))?;
+    writeln!(out, "elapsed_secs,{:.1}", summary.elapsed_secs)?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    fn records() -> Vec<ReportRecord> {
+        let mut processed = ReportRecord::new("a.wav", FileStatus::Processed);
+        processed.endpoint = Some("http://h/vad".to_string());
+        processed.http_status = Some(200);
+        processed.latency_ms = Some(100);
+        processed.audio_secs = Some(1800.0);
+        processed.segments = Some(2);
+        processed.speech_secs = Some(1.25);
+
+        let mut invalid = ReportRecord::new("dir, \"odd\"/b.wav", FileStatus::InvalidFormat);
+        invalid.rejections = vec![
+            Rejection::Empty,
+            Rejection::DcOffset {
+                offset: 0.1,
+                max_offset: 0.05,
+            },
+        ];
+
+        let mut failed = ReportRecord::new("c.wav", FileStatus::HttpError);
+        failed.endpoint = Some("http://h/vad".to_string());
+        failed.http_status = Some(503);
+        failed.latency_ms = Some(300);
+        failed.error = Some("VAD failed: API returned status 503".to_string());
+
+        vec![processed, invalid, failed]
+    }
+
+    fn write(path: &Path, format: OutputFormat) -> ReportSummary {
+        let report = RunReport::create(path, format).unwrap();
+        for record in records() {
+            report.record(&record).unwrap();
+        }
+        report.finish(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn summary_counts_statuses_and_latency() {
+        let dir = TempDir::new();
+        let summary = write(&dir.path().join("report.json"), OutputFormat::Json);
+        assert_eq!(
+            summary,
+            ReportSummary {
+                files: 3,
+                processed: 1,
+                invalid_format: 1,
+                http_error: 1,
+                audio_hours: 0.5,
+                total_latency_ms: 400,
+                mean_latency_ms: Some(200.0),
+                elapsed_secs: 5.0,
+                ..ReportSummary::default()
+            }
+        );
+    }
+
+    #[test]
+    fn json_report_holds_files_and_summary() {
+        let dir = TempDir::new();
+        let path = dir.path().join("report.json");
+        write(&path, OutputFormat::Json);
+
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(json["files"].as_array().unwrap().len(), 3);
+        assert_eq!(json["files"][0]["status"], "processed");
+        assert_eq!(json["files"][0]["segments"], 2);
+        assert_eq!(json["files"][1]["rejections"][0]["reason"], "empty");
+        assert!(json["files"][0].get("error").is_none());
+        assert_eq!(json["summary"]["files"], 3);
+        assert_eq!(json["summary"]["mean_latency_ms"], 200.0);
+    }
+
+    #[test]
+    fn empty_json_report_is_valid() {
+        let dir = TempDir::new();
+        let path = dir.path().join("report.json");
+        RunReport::create(&path, OutputFormat::Json)
+            .unwrap()
+            .finish(Duration::ZERO)
+            .unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(json["files"], serde_json::json!([]));
+        assert_eq!(json["summary"]["mean_latency_ms"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn csv_report_quotes_fields_and_writes_summary_sidecar() {
+        let dir = TempDir::new();
+        let path = dir.path().join("report.csv");
+        write(&path, OutputFormat::Csv);
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "input,status,endpoint,http_status,latency_ms,audio_secs,segments,speech_secs,error\n\
+             a.wav,processed,http://h/vad,200,100,1800.000,2,1.250,\n\
+             \"dir, \"\"odd\"\"/b.wav\",invalid_format,,,,,,,no audio; DC offset (0.100 > 0.050)\n\
+             c.wav,http_error,http://h/vad,503,300,,,,VAD failed: API returned status 503\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("report.summary.csv")).unwrap(),
+            "name,value\nfiles,3\nprocessed,1\nalready_done,0\ninvalid_format,1\nhttp_error,1\n\
+             io_error,0\naudio_hours,0.5000\ntotal_latency_ms,400\nmean_latency_ms,200.0\n\
+             elapsed_secs,5.0\n"
+        );
+    }
+
+    #[test]
+    fn summary_path_is_only_set_for_csv() {
+        let dir = TempDir::new();
+        let csv = RunReport::create(dir.path().join("run.csv"), OutputFormat::Csv).unwrap();
+        assert_eq!(csv.summary_path(), Some(dir.path().join("run.summary.csv")));
+        let json = RunReport::create(dir.path().join("run.json"), OutputFormat::Json).unwrap();
+        assert_eq!(json.summary_path(), None);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// A speech segment detected by the VAD server, in seconds from the start of the file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    /// Speech probability reported for the segment, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f64>,
+}
+
+impl Segment {
+    /// Length of the segment in seconds.
+    pub fn duration(&self) -> f64 {
+        (self.end - self.start).max(0.0)
+    }
+}
+
+/// JSON body returned by the VAD API.
+///
+/// ```json
+/// {
+///   "segments": [{ "start": 0.48, "end": 2.91, "probability": 0.97 }],
+///   "output_files": ["/data/out/a.wav/a/segment_000.wav"]
+/// }
+/// ```
+///
+/// Both fields are optional so that servers returning only one of them are accepted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VadResponse {
+    /// Detected speech segments.
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+    /// Files the server wrote, as paths on the server.
+    #[serde(default)]
+    pub output_files: Vec<String>,
+}
+
+impl VadResponse {
+    /// Total seconds of detected speech.
+    pub fn speech_seconds(&self) -> f64 {
+        self.segments.iter().map(Segment::duration).sum()
+    }
+}
+
+/// Result of running VAD on one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VadOutput {
+    /// Endpoint that served the request, or `local` for the built-in engine.
+    pub endpoint: String,
+    /// HTTP status code, if the file went through the API.
+    pub status: Option<u16>,
+    /// Parsed JSON body, or `None` when the server returned audio or no JSON.
+    pub body: Option<VadResponse>,
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+/// Status codes retried by default: request timeout, rate limiting and transient server errors.
+pub const DEFAULT_RETRYABLE_STATUSES: &[u16] = &[408, 429, 500, 502, 503, 504];
+
+/// Controls how failed VAD API calls are retried.
+///
+/// Delays grow exponentially from `base_delay`, are capped at `max_delay`, and
+/// are reduced by a random fraction of up to `jitter` so that workers hitting
+/// the same failing server do not retry in lockstep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts per file, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+    /// Upper bound for any single delay.
+    pub max_delay: Duration,
+    /// Fraction in `0.0..=1.0` of each delay that is randomised.
+    pub jitter: f64,
+    /// HTTP status codes that trigger a retry.
+    pub retryable_statuses: Vec<u16>,
+    /// Whether connection, timeout and other I/O errors trigger a retry.
+    pub retry_io_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retryable_statuses: DEFAULT_RETRYABLE_STATUSES.to_vec(),
+            retry_io_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes a single attempt and never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay to wait before retry number `retry` (starting at 1).
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+
+        delay.mul_f64(1.0 - jitter * fastrand::f64())
+    }
+
+    /// Whether `status` is configured as retryable.
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    /// Whether a failed call with the given error should be retried.
+    pub fn is_retryable(&self, err: &ureq::Error) -> bool {
+        match err {
+            ureq::Error::StatusCode(status) => self.is_retryable_status(*status),
+            ureq::Error::Io(_)
+            | ureq::Error::Timeout(_)
+            | ureq::Error::HostNotFound
+            | ureq::Error::ConnectionFailed
+            | ureq::Error::Protocol(_) => self.retry_io_errors,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1_000),
+            jitter,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let policy = policy(0.0);
+        let delays: Vec<u64> = (1..=6)
+            .map(|retry| policy.delay_for(retry).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1_000, 1_000]);
+        assert_eq!(policy.delay_for(0), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(u32::MAX), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn jitter_only_shortens_delays() {
+        let policy = policy(0.5);
+        for _ in 0..200 {
+            let delay = policy.delay_for(3);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+        // Out-of-range jitter is clamped to the full delay.
+        for _ in 0..200 {
+            assert!(
+                RetryPolicy {
+                    jitter: 7.0,
+                    ..policy.clone()
+                }
+                .delay_for(1)
+                    <= Duration::from_millis(100)
+            );
+        }
+    }
+
+    #[test]
+    fn statuses_are_retried_as_configured() {
+        let policy = RetryPolicy::default();
+        for status in [408, 429, 500, 503] {
+            assert!(policy.is_retryable(&ureq::Error::StatusCode(status)));
+        }
+        for status in [400, 404, 501] {
+            assert!(!policy.is_retryable(&ureq::Error::StatusCode(status)));
+        }
+
+        let policy = RetryPolicy {
+            retryable_statuses: vec![404],
+            ..RetryPolicy::default()
+        };
+        assert!(policy.is_retryable(&ureq::Error::StatusCode(404)));
+        assert!(!policy.is_retryable(&ureq::Error::StatusCode(503)));
+    }
+
+    #[test]
+    fn io_errors_follow_retry_io_errors() {
+        let errors = || {
+            [
+                ureq::Error::ConnectionFailed,
+                ureq::Error::HostNotFound,
+                ureq::Error::Timeout(ureq::Timeout::Global),
+                ureq::Error::Io(std::io::ErrorKind::ConnectionReset.into()),
+            ]
+        };
+        let policy = RetryPolicy::default();
+        assert!(errors().iter().all(|e| policy.is_retryable(e)));
+
+        let policy = RetryPolicy {
+            retry_io_errors: false,
+            ..RetryPolicy::default()
+        };
+        assert!(!errors().iter().any(|e| policy.is_retryable(e)));
+        assert!(!policy.is_retryable(&ureq::Error::BadUri("x".to_string())));
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+/// Controls how long VAD API calls may take.
+///
+/// The request budget is `request` plus `per_audio_minute` for every minute
+/// of audio in the file, so long recordings get proportionally more time.
+/// `None` leaves the corresponding limit off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    /// Time allowed to resolve the host and open the connection.
+    pub connect: Option<Duration>,
+    /// Time allowed for a whole request, from connecting to reading the response body.
+    pub request: Option<Duration>,
+    /// Extra request time per minute of audio. Meant to extend `request`: on
+    /// its own, short files get budgets too small for any server.
+    pub per_audio_minute: Option<Duration>,
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        Self {
+            connect: Some(Duration::from_secs(10)),
+            request: None,
+            per_audio_minute: None,
+        }
+    }
+}
+
+impl TimeoutPolicy {
+    /// A policy without any timeout.
+    pub fn none() -> Self {
+        Self {
+            connect: None,
+            request: None,
+            per_audio_minute: None,
+        }
+    }
+
+    /// Request budget for a file with `audio` of audio, if it is known.
+    pub fn request_budget(&self, audio: Option<Duration>) -> Option<Duration> {
+        let scaled = self
+            .per_audio_minute
+            .zip(audio)
+            .map(|(per_minute, audio)| per_minute.mul_f64(audio.as_secs_f64() / 60.0));
+        match (self.request, scaled) {
+            (Some(request), Some(scaled)) => Some(request.saturating_add(scaled)),
+            (request, scaled) => request.or(scaled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn policy(request: Option<u64>, per_audio_minute: Option<u64>) -> TimeoutPolicy {
+        TimeoutPolicy {
+            request: request.map(Duration::from_secs),
+            per_audio_minute: per_audio_minute.map(Duration::from_secs),
+            ..TimeoutPolicy::default()
+        }
+    }
+
+    #[test]
+    fn budget_without_limits_is_unbounded() {
+        assert_eq!(TimeoutPolicy::none().request_budget(Some(MINUTE)), None);
+        assert_eq!(TimeoutPolicy::default().request_budget(Some(MINUTE)), None);
+    }
+
+    #[test]
+    fn fixed_budget_ignores_audio() {
+        let policy = policy(Some(30), None);
+        assert_eq!(policy.request_budget(None), Some(Duration::from_secs(30)));
+        assert_eq!(
+            policy.request_budget(Some(10 * MINUTE)),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn budget_scales_with_audio() {
+        let policy = policy(None, Some(4));
+        assert_eq!(
+            policy.request_budget(Some(MINUTE / 2)),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(policy.request_budget(None), None);
+
+        let policy = self::policy(Some(30), Some(4));
+        assert_eq!(
+            policy.request_budget(Some(3 * MINUTE)),
+            Some(Duration::from_secs(42))
+        );
+        assert_eq!(policy.request_budget(None), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn budget_saturates() {
+        let policy = TimeoutPolicy {
+            request: Some(Duration::MAX),
+            per_audio_minute: Some(Duration::from_secs(1)),
+            ..TimeoutPolicy::default()
+        };
+        assert_eq!(policy.request_budget(Some(MINUTE)), Some(Duration::MAX));
+    }
+}
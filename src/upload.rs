@@ -0,0 +1,110 @@
+use crate::response::VadResponse;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use ureq::Body;
+use ureq::http::Response;
+
+/// File name of locally written speech audio returned in upload mode.
+pub const SPEECH_AUDIO_FILE: &str = "speech.wav";
+/// File name of locally written segment timestamps.
+pub const SEGMENTS_FILE: &str = "segments.json";
+
+/// How input audio reaches the VAD server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RequestMode {
+    /// Send local `input_file`/`output_dir` paths as JSON; the server reads and
+    /// writes our filesystem directly.
+    #[default]
+    SharedPath,
+    /// Upload the WAV as `multipart/form-data` (`file` part, optional `model` field).
+    Multipart,
+    /// Upload the WAV as the raw `audio/wav` request body (model in the `X-Vad-Model` header).
+    Raw,
+}
+
+impl RequestMode {
+    /// Whether audio bytes are uploaded instead of shared paths.
+    pub fn is_upload(self) -> bool {
+        self != RequestMode::SharedPath
+    }
+}
+
+/// Builds a `multipart/form-data` body with the WAV file and optional model field.
+///
+/// Returns the body together with its `Content-Type` header value.
+pub fn multipart_body(file_name: &str, audio: &[u8], model: Option<&str>) -> (String, Vec<u8>) {
+    let boundary = format!("----wav-files-vad-api-{:016x}", fastrand::u64(..));
+    let file_name = file_name.replace('"', "_");
+    let mut body = Vec::with_capacity(audio.len() + 512);
+
+    if let Some(model) = model {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\n{model}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\nContent-Type: audio/wav\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(audio);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+/// Writes a parsed response as [`SEGMENTS_FILE`] into `dir`, creating it if
+/// needed, and returns the path of the written file.
+pub fn write_segments(dir: &Path, response: &VadResponse) -> Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
+    let path = dir.join(SEGMENTS_FILE);
+    fs::write(&path, serde_json::to_vec_pretty(response)?)
+        .with_context(|| format!("Failed to write: {}", path.display()))?;
+    Ok(path)
+}
+
+/// Writes an upload-mode response into `dir`, creating it if needed.
+///
+/// Audio responses are stored as [`SPEECH_AUDIO_FILE`], JSON responses as
+/// [`SEGMENTS_FILE`]. Returns the path of the written file and, for JSON
+/// responses, the parsed body.
+pub fn write_response(
+    resp: &mut Response<Body>,
+    dir: &Path,
+) -> Result<(PathBuf, Option<VadResponse>)> {
+    let body = resp.body_mut();
+    let mime = body.mime_type().unwrap_or_default().to_ascii_lowercase();
+
+    let file_name = if mime.starts_with("audio/") {
+        SPEECH_AUDIO_FILE
+    } else if mime == "application/json" || mime.ends_with("+json") {
+        SEGMENTS_FILE
+    } else {
+        anyhow::bail!("Unsupported response content type: {mime:?}");
+    };
+
+    let data = body
+        .with_config()
+        .limit(u64::MAX)
+        .read_to_vec()
+        .context("Failed to read VAD API response body")?;
+
+    let parsed = if file_name == SEGMENTS_FILE {
+        Some(serde_json::from_slice(&data).context("Invalid VAD API response JSON")?)
+    } else {
+        None
+    };
+
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory: {}", dir.display()))?;
+    let path = dir.join(file_name);
+    fs::write(&path, data).with_context(|| format!("Failed to write: {}", path.display()))?;
+
+    Ok((path, parsed))
+}
@@ -0,0 +1,465 @@
+use crate::wav::SourceFormat;
+use anyhow::{Context, Result};
+use hound::{WavReader, WavSpec};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Absolute sample level, relative to full scale, counted as clipped.
+const CLIP_LEVEL: f32 = 0.999;
+
+/// How thoroughly input files are checked before they reach the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ValidationMode {
+    /// Check the header only: channels, sample rate and sample format
+    #[default]
+    Header,
+    /// Also read every sample: truncation, duration limits, silence, clipping and DC offset
+    Deep,
+}
+
+/// Thresholds applied by [`ValidationMode::Deep`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationLimits {
+    /// Shortest accepted file.
+    pub min_duration: Duration,
+    /// Longest accepted file, if limited.
+    pub max_duration: Option<Duration>,
+    /// Files whose peak stays below this level in dBFS are silent.
+    pub silence_threshold_db: f32,
+    /// Largest accepted fraction of samples at full scale.
+    pub max_clipped_fraction: f64,
+    /// Largest accepted mean sample value, as a fraction of full scale.
+    pub max_dc_offset: f64,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        Self {
+            min_duration: Duration::ZERO,
+            max_duration: None,
+            silence_threshold_db: -60.0,
+            max_clipped_fraction: 0.001,
+            max_dc_offset: 0.05,
+        }
+    }
+}
+
+/// Why a file was rejected, as recorded in the journal.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Rejection {
+    /// The header describes a format that is neither the target nor convertible.
+    Format {
+        channels: u16,
+        sample_rate: u32,
+        sample_format: String,
+    },
+    /// The data chunk declares more samples than the file holds.
+    Truncated {
+        declared_samples: u64,
+        read_samples: u64,
+    },
+    /// Samples could not be decoded.
+    Corrupt {
+        error: String,
+    },
+    /// The file holds no audio at all.
+    Empty,
+    TooShort {
+        seconds: f64,
+        min_seconds: f64,
+    },
+    TooLong {
+        seconds: f64,
+        max_seconds: f64,
+    },
+    /// The peak level is below the silence threshold.
+    Silent {
+        peak_db: f64,
+        threshold_db: f64,
+    },
+    Clipped {
+        fraction: f64,
+        max_fraction: f64,
+    },
+    DcOffset {
+        offset: f64,
+        max_offset: f64,
+    },
+}
+
+impl Rejection {
+    /// Rejection for a header that cannot be processed.
+    pub fn format(spec: &WavSpec) -> Self {
+        Rejection::Format {
+            channels: spec.channels,
+            sample_rate: spec.sample_rate,
+            sample_format: SourceFormat::of(spec).to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Format {
+                channels,
+                sample_rate,
+                sample_format,
+            } => write!(
+                f,
+                "not 16 kHz mono 16-bit PCM ({channels} channel(s), {sample_rate} Hz, {sample_format})"
+            ),
+            Rejection::Truncated {
+                declared_samples,
+                read_samples,
+            } => write!(
+                f,
+                "truncated ({read_samples} of {declared_samples} declared samples)"
+            ),
+            Rejection::Corrupt { error } => write!(f, "corrupt samples ({error})"),
+            Rejection::Empty => write!(f, "no audio"),
+            Rejection::TooShort {
+                seconds,
+                min_seconds,
+            } => write!(f, "too short ({seconds:.3}s < {min_seconds:.3}s)"),
+            Rejection::TooLong {
+                seconds,
+                max_seconds,
+            } => write!(f, "too long ({seconds:.3}s > {max_seconds:.3}s)"),
+            Rejection::Silent {
+                peak_db,
+                threshold_db,
+            } => write!(
+                f,
+                "silent (peak {peak_db:.1} dBFS < {threshold_db:.1} dBFS)"
+            ),
+            Rejection::Clipped {
+                fraction,
+                max_fraction,
+            } => write!(
+                f,
+                "clipped ({:.3}% of samples > {:.3}%)",
+                fraction * 100.0,
+                max_fraction * 100.0
+            ),
+            Rejection::DcOffset { offset, max_offset } => {
+                write!(f, "DC offset ({offset:.3} > {max_offset:.3})")
+            }
+        }
+    }
+}
+
+/// Running level statistics over interleaved samples in `-1.0..=1.0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SampleStats {
+    /// Number of samples seen, across all channels.
+    pub samples: u64,
+    /// Largest absolute sample value.
+    pub peak: f32,
+    /// Samples at or above [`CLIP_LEVEL`].
+    pub clipped: u64,
+    sum: f64,
+}
+
+impl SampleStats {
+    /// Collects statistics over `samples`.
+    pub fn of(samples: &[f32]) -> Self {
+        let mut stats = Self::default();
+        for &sample in samples {
+            stats.push(sample);
+        }
+        stats
+    }
+
+    /// Adds one sample.
+    pub fn push(&mut self, sample: f32) {
+        let level = sample.abs();
+        self.samples += 1;
+        self.peak = self.peak.max(level);
+        self.sum += f64::from(sample);
+        if level >= CLIP_LEVEL {
+            self.clipped += 1;
+        }
+    }
+
+    /// Mean sample value.
+    pub fn dc_offset(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.sum / self.samples as f64
+        }
+    }
+
+    /// Duration of the audio at `sample_rate` with `channels` interleaved channels.
+    pub fn duration_secs(&self, channels: u16, sample_rate: u32) -> f64 {
+        if channels == 0 || sample_rate == 0 {
+            return 0.0;
+        }
+        self.samples as f64 / f64::from(channels) / f64::from(sample_rate)
+    }
+
+    /// Checks the statistics against `limits`, returning every violation.
+    pub fn check(
+        &self,
+        channels: u16,
+        sample_rate: u32,
+        limits: &ValidationLimits,
+    ) -> Vec<Rejection> {
+        if self.samples == 0 {
+            return vec![Rejection::Empty];
+        }
+
+        let mut rejections = Vec::new();
+
+        let seconds = self.duration_secs(channels, sample_rate);
+        let min_seconds = limits.min_duration.as_secs_f64();
+        if seconds < min_seconds {
+            rejections.push(Rejection::TooShort {
+                seconds,
+                min_seconds,
+            });
+        }
+        if let Some(max) = limits.max_duration
+            && seconds > max.as_secs_f64()
+        {
+            rejections.push(Rejection::TooLong {
+                seconds,
+                max_seconds: max.as_secs_f64(),
+            });
+        }
+
+        let peak_db = 20.0 * f64::from(self.peak).max(1e-10).log10();
+        let threshold_db = f64::from(limits.silence_threshold_db);
+        if peak_db < threshold_db {
+            rejections.push(Rejection::Silent {
+                peak_db,
+                threshold_db,
+            });
+        }
+
+        let fraction = self.clipped as f64 / self.samples as f64;
+        if fraction > limits.max_clipped_fraction {
+            rejections.push(Rejection::Clipped {
+                fraction,
+                max_fraction: limits.max_clipped_fraction,
+            });
+        }
+
+        let offset = self.dc_offset().abs();
+        if offset > limits.max_dc_offset {
+            rejections.push(Rejection::DcOffset {
+                offset,
+                max_offset: limits.max_dc_offset,
+            });
+        }
+
+        rejections
+    }
+}
+
+/// Reads every sample of a WAV file and checks it against `limits`.
+///
+/// Returns every problem found, or an empty list for a sound file. Files with
+/// a sample format that cannot be decoded are rejected as [`Rejection::Format`];
+/// only failures to open the file are returned as errors.
+pub fn deep_validate_wav(path: &Path, limits: &ValidationLimits) -> Result<Vec<Rejection>> {
+    let mut reader = WavReader::open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    let spec = reader.spec();
+    let declared_samples = u64::from(reader.len());
+
+    let mut stats = SampleStats::default();
+    let read_error = match SourceFormat::of(&spec) {
+        SourceFormat::Unsupported { .. } => return Ok(vec![Rejection::format(&spec)]),
+        SourceFormat::Float32 => read_into(reader.samples::<f32>(), &mut stats),
+        _ => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            read_into(
+                reader.samples::<i32>().map(|s| s.map(|s| s as f32 / scale)),
+                &mut stats,
+            )
+        }
+    };
+
+    let mut rejections = Vec::new();
+    match read_error {
+        // `hound` reports short reads inside the data chunk as I/O errors
+        // (`ErrorKind::Other`), or as `UnexpectedEof` from the underlying reader.
+        Some(hound::Error::IoError(_)) => {
+            rejections.push(Rejection::Truncated {
+                declared_samples,
+                read_samples: stats.samples,
+            });
+        }
+        Some(e) => rejections.push(Rejection::Corrupt {
+            error: e.to_string(),
+        }),
+        None if stats.samples < declared_samples => rejections.push(Rejection::Truncated {
+            declared_samples,
+            read_samples: stats.samples,
+        }),
+        None => {}
+    }
+
+    rejections.extend(stats.check(spec.channels, spec.sample_rate, limits));
+    Ok(rejections)
+}
+
+/// Feeds samples into `stats` until the first read error, which is returned.
+fn read_into(
+    samples: impl Iterator<Item = hound::Result<f32>>,
+    stats: &mut SampleStats,
+) -> Option<hound::Error> {
+    for sample in samples {
+        match sample {
+            Ok(sample) => stats.push(sample),
+            Err(e) => return Some(e),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use hound::SampleFormat;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const SPEC: WavSpec = WavSpec {
+        channels: 1,
+        sample_rate: 16_000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    /// Writes `samples` as a 16 kHz mono 16-bit WAV named `name` in `dir`.
+    fn wav(dir: &TempDir, name: &str, samples: impl IntoIterator<Item = i16>) -> PathBuf {
+        dir.write_wav(name, SPEC, samples)
+    }
+
+    /// One second of a 440 Hz tone at half scale, plus `offset`.
+    fn tone(offset: i16) -> impl Iterator<Item = i16> {
+        (0..16_000).map(move |n| {
+            let phase = std::f32::consts::TAU * 440.0 * n as f32 / 16_000.0;
+            (16_000.0 * phase.sin()) as i16 + offset
+        })
+    }
+
+    fn reasons(rejections: &[Rejection]) -> Vec<&'static str> {
+        rejections
+            .iter()
+            .map(|r| match r {
+                Rejection::Format { .. } => "format",
+                Rejection::Truncated { .. } => "truncated",
+                Rejection::Corrupt { .. } => "corrupt",
+                Rejection::Empty => "empty",
+                Rejection::TooShort { .. } => "too_short",
+                Rejection::TooLong { .. } => "too_long",
+                Rejection::Silent { .. } => "silent",
+                Rejection::Clipped { .. } => "clipped",
+                Rejection::DcOffset { .. } => "dc_offset",
+            })
+            .collect()
+    }
+
+    fn validate(path: &Path) -> Vec<&'static str> {
+        reasons(&deep_validate_wav(path, &ValidationLimits::default()).unwrap())
+    }
+
+    #[test]
+    fn sound_file_passes() {
+        let dir = TempDir::new();
+        assert!(validate(&wav(&dir, "sound.wav", tone(0))).is_empty());
+    }
+
+    #[test]
+    fn empty_file_is_rejected() {
+        let dir = TempDir::new();
+        assert_eq!(validate(&wav(&dir, "empty.wav", [])), ["empty"]);
+    }
+
+    #[test]
+    fn silent_file_is_rejected() {
+        let dir = TempDir::new();
+        assert_eq!(validate(&wav(&dir, "silent.wav", [0; 16_000])), ["silent"]);
+        // -60 dBFS is about 33 at 16 bits.
+        assert_eq!(validate(&wav(&dir, "quiet.wav", [20; 16_000])), ["silent"]);
+    }
+
+    #[test]
+    fn clipped_file_is_rejected() {
+        let dir = TempDir::new();
+        let samples = tone(0).enumerate().map(|(n, s)| match n % 100 {
+            0 => i16::MAX,
+            1 => i16::MIN,
+            _ => s,
+        });
+        assert_eq!(validate(&wav(&dir, "clipped.wav", samples)), ["clipped"]);
+    }
+
+    #[test]
+    fn dc_offset_is_rejected() {
+        let dir = TempDir::new();
+        assert_eq!(validate(&wav(&dir, "dc.wav", tone(3_000))), ["dc_offset"]);
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let dir = TempDir::new();
+        let path = wav(&dir, "truncated.wav", tone(0));
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1_001]).unwrap();
+
+        let rejections = deep_validate_wav(&path, &ValidationLimits::default()).unwrap();
+        assert_eq!(reasons(&rejections), ["truncated"]);
+        assert!(matches!(
+            rejections[0],
+            Rejection::Truncated {
+                declared_samples: 16_000,
+                read_samples,
+            } if read_samples < 16_000
+        ));
+    }
+
+    #[test]
+    fn duration_limits_are_applied() {
+        let dir = TempDir::new();
+        let path = wav(&dir, "second.wav", tone(0));
+        let limits = ValidationLimits {
+            min_duration: Duration::from_secs(2),
+            ..ValidationLimits::default()
+        };
+        assert_eq!(
+            reasons(&deep_validate_wav(&path, &limits).unwrap()),
+            ["too_short"]
+        );
+        let limits = ValidationLimits {
+            max_duration: Some(Duration::from_millis(500)),
+            ..ValidationLimits::default()
+        };
+        assert_eq!(
+            reasons(&deep_validate_wav(&path, &limits).unwrap()),
+            ["too_long"]
+        );
+    }
+
+    #[test]
+    fn check_reports_every_violation() {
+        let stats = SampleStats::of(&[1.0; 10]);
+        assert_eq!(stats.duration_secs(2, 5), 1.0);
+        assert_eq!(
+            reasons(&stats.check(1, 16_000, &ValidationLimits::default())),
+            ["clipped", "dc_offset"]
+        );
+        assert_eq!(
+            reasons(&SampleStats::default().check(1, 16_000, &ValidationLimits::default())),
+            ["empty"]
+        );
+    }
+}
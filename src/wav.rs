@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavReader, WavSpec};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Sample rate the VAD backends expect.
+pub const TARGET_SAMPLE_RATE: u32 = 16000;
+
+/// Sample encoding of a WAV file, derived from its `hound::SampleFormat` and bit depth.
+///
+/// `WAVE_FORMAT_EXTENSIBLE` headers are resolved by `hound` to the same spec
+/// as their plain counterparts, so they map to the same variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Pcm8,
+    Pcm16,
+    Pcm24,
+    Pcm32,
+    Float32,
+    /// Any other combination, e.g. 16-bit float or 12-bit integer samples.
+    Unsupported {
+        sample_format: SampleFormat,
+        bits_per_sample: u16,
+    },
+}
+
+impl SourceFormat {
+    /// Classifies the sample encoding of `spec`.
+    pub fn of(spec: &WavSpec) -> Self {
+        match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, 8) => SourceFormat::Pcm8,
+            (SampleFormat::Int, 16) => SourceFormat::Pcm16,
+            (SampleFormat::Int, 24) => SourceFormat::Pcm24,
+            (SampleFormat::Int, 32) => SourceFormat::Pcm32,
+            (SampleFormat::Float, 32) => SourceFormat::Float32,
+            (sample_format, bits_per_sample) => SourceFormat::Unsupported {
+                sample_format,
+                bits_per_sample,
+            },
+        }
+    }
+
+    /// Whether samples in this encoding can be decoded for conversion.
+    pub fn is_supported(self) -> bool {
+        !matches!(self, SourceFormat::Unsupported { .. })
+    }
+}
+
+impl fmt::Display for SourceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceFormat::Pcm8 => write!(f, "8-bit PCM"),
+            SourceFormat::Pcm16 => write!(f, "16-bit PCM"),
+            SourceFormat::Pcm24 => write!(f, "24-bit PCM"),
+            SourceFormat::Pcm32 => write!(f, "32-bit PCM"),
+            SourceFormat::Float32 => write!(f, "32-bit float"),
+            SourceFormat::Unsupported {
+                sample_format,
+                bits_per_sample,
+            } => write!(f, "{bits_per_sample}-bit {sample_format:?} (unsupported)"),
+        }
+    }
+}
+
+/// Reads the header of a WAV file.
+pub fn wav_spec(path: &Path) -> Result<WavSpec> {
+    Ok(wav_header(path)?.0)
+}
+
+/// Reads the header of a WAV file and the duration its data chunk declares.
+pub fn wav_header(path: &Path) -> Result<(WavSpec, Duration)> {
+    let reader = WavReader::open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    let spec = reader.spec();
+    let duration = if spec.sample_rate == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(f64::from(reader.duration()) / f64::from(spec.sample_rate))
+    };
+
+    Ok((spec, duration))
+}
+
+/// Whether `spec` is the format the VAD backends expect: mono, 16-bit PCM, 16kHz sample rate.
+pub fn is_target_format(spec: &WavSpec) -> bool {
+    spec.channels == 1
+        && spec.sample_rate == TARGET_SAMPLE_RATE
+        && SourceFormat::of(spec) == SourceFormat::Pcm16
+}
+
+/// Validates a WAV file matches the expected format: mono, 16-bit PCM, 16kHz sample rate.
+pub fn validate_wav(path: &Path) -> Result<bool> {
+    Ok(is_target_format(&wav_spec(path)?))
+}